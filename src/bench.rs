@@ -0,0 +1,83 @@
+//! Headless runs of the search: a benchmark on a saved map, run with
+//! `--load <map> --bench <n>`, and a solver for grids piped in with `--stdin`, printing the
+//! path as CSV or, with `--ascii`, the grid with the path marked.
+
+use std::io::Read as _;
+use std::path::Path;
+use std::time::Instant;
+
+use crate::export;
+use crate::map;
+use crate::pathfind::{OpenList, Options, Search};
+
+/// Runs the search on the map at `path` `iterations` times and prints the averages. The
+/// timing is repeated with each kind of open list, which expand the same cells.
+pub fn run(path: &Path, iterations: u32, options: &Options) -> Result<(), String> {
+    let map = map::load(path).map_err(|err| format!("failed to load {}: {err}", path.display()))?;
+    let Some(start) = map.start.filter(|_| !map.ends.is_empty()) else {
+        return Err(format!("{} has no start or end", path.display()));
+    };
+
+    let mut total_numcalc = 0;
+    let mut total_times_ms = [0.0; 2];
+    let mut cost = None;
+    let mut search = Search::new(&map.grid, start, &map.ends, options);
+    for (open_list, total_time_ms) in [OpenList::Heap, OpenList::Scan]
+        .into_iter()
+        .zip(&mut total_times_ms)
+    {
+        let options = Options {
+            open_list,
+            ..*options
+        };
+        total_numcalc = 0;
+        for _ in 0..iterations {
+            let started = Instant::now();
+            search.restart(&map.grid, start, &map.ends, &options);
+            search.run(&map.grid);
+            *total_time_ms += started.elapsed().as_secs_f64() * 1000.0;
+            total_numcalc += search.result().numcalc;
+            cost = search.result().path.as_ref().map(|_| search.result().cost);
+        }
+    }
+
+    println!("map: {}", path.display());
+    println!("algorithm: {:?}", options.algorithm);
+    println!("iterations: {iterations}");
+    match cost {
+        Some(cost) => println!("pathcost: {cost}"),
+        None => println!("pathcost: no path"),
+    }
+    println!("numcalc: {:.1}", total_numcalc as f64 / iterations as f64);
+    let [heap_time_ms, scan_time_ms] = total_times_ms.map(|time_ms| time_ms / iterations as f64);
+    println!("time: {heap_time_ms:.3} ms with a heap, {scan_time_ms:.3} ms with a scanned list");
+    Ok(())
+}
+
+/// Reads a bare grid as described in [`map`] from standard input and prints its path in the
+/// CSV format of [`export::path_csv`], or the solved grid as [`export::ascii`] if `ascii`.
+pub fn solve_stdin(options: &Options, ascii: bool) -> Result<(), String> {
+    let mut input = String::new();
+    std::io::stdin()
+        .read_to_string(&mut input)
+        .map_err(|err| format!("failed to read standard input: {err}"))?;
+    let map = map::parse_ascii(&input)?;
+    let Some(start) = map.start.filter(|_| !map.ends.is_empty()) else {
+        return Err("the grid has no start or end".to_owned());
+    };
+
+    let mut search = Search::new(&map.grid, start, &map.ends, options);
+    search.run(&map.grid);
+    let result = search.result();
+    if ascii {
+        let path = result.path.as_deref().unwrap_or_default();
+        print!("{}", export::ascii(&map.grid, Some(start), &map.ends, path));
+    } else {
+        let route = result
+            .path
+            .as_deref()
+            .map(|path| (start, path, result.cost));
+        print!("{}", export::path_csv(route));
+    }
+    Ok(())
+}
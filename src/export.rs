@@ -0,0 +1,175 @@
+//! Writing computed paths and grid snapshots to files for use in other tools.
+
+use std::fmt::Write as _;
+use std::path::Path;
+
+use crate::pathfind::{Grid, Pos};
+
+/// `row,col` per visited cell starting at `start`, after a comment line with the total cost.
+/// Without a path only a comment saying so is written.
+pub fn path_csv(route: Option<(Pos, &[Pos], u64)>) -> String {
+    let Some((start, path, cost)) = route else {
+        return "# no path\n".to_owned();
+    };
+
+    let mut s = String::new();
+    writeln!(s, "# cost {cost}").unwrap();
+    writeln!(s, "row,col").unwrap();
+    for Pos(r, c) in std::iter::once(&start).chain(path) {
+        writeln!(s, "{r},{c}").unwrap();
+    }
+    s
+}
+
+pub fn save_path_csv(path: &Path, route: Option<(Pos, &[Pos], u64)>) -> std::io::Result<()> {
+    std::fs::write(path, path_csv(route))
+}
+
+/// The grid in the bare format [`crate::map::parse_ascii`] reads, with `*` on the cells of
+/// `path` besides its end. Weighted and one-way cells are written as open ground.
+pub fn ascii(grid: &Grid, start: Option<Pos>, ends: &[Pos], path: &[Pos]) -> String {
+    let mut s = String::new();
+    for r in 0..grid.rows() as i64 {
+        for c in 0..grid.cols() as i64 {
+            let pos = Pos(r, c);
+            s.push(if start == Some(pos) {
+                'S'
+            } else if ends.contains(&pos) {
+                'E'
+            } else if path.contains(&pos) {
+                '*'
+            } else if grid.is_wall(pos) {
+                '#'
+            } else {
+                '.'
+            });
+        }
+        s.push('\n');
+    }
+    s
+}
+
+pub fn save_ascii(
+    path: &Path,
+    grid: &Grid,
+    start: Option<Pos>,
+    ends: &[Pos],
+    route: &[Pos],
+) -> std::io::Result<()> {
+    std::fs::write(path, ascii(grid, start, ends, route))
+}
+
+/// SVG drawing of the grid with one unit per cell, in the same colors as the window.
+pub fn svg(grid: &Grid, wrap: bool, start: Option<Pos>, ends: &[Pos], path: &[Pos]) -> String {
+    let (rows, cols) = (grid.rows(), grid.cols());
+    let mut s = String::new();
+    writeln!(
+        s,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 {cols} {rows}">"#
+    )
+    .unwrap();
+    writeln!(s, r#"<rect width="{cols}" height="{rows}" fill="black"/>"#).unwrap();
+    for r in 0..rows as i64 {
+        for c in 0..cols as i64 {
+            if grid.is_wall(Pos(r, c)) {
+                writeln!(
+                    s,
+                    r#"<rect x="{c}" y="{r}" width="1" height="1" fill="rgb(230,230,230)"/>"#
+                )
+                .unwrap();
+            }
+        }
+    }
+
+    write!(s, r#"<path stroke="white" stroke-width="0.05" d=""#).unwrap();
+    for r in 0..=rows {
+        write!(s, "M0 {r}H{cols}").unwrap();
+    }
+    for c in 0..=cols {
+        write!(s, "M{c} 0V{rows}").unwrap();
+    }
+    writeln!(s, r#""/>"#).unwrap();
+
+    if let Some(start) = start {
+        // steps through portals or across wrapping edges break the line
+        let mut points = Vec::new();
+        let mut prev = start;
+        for &p in std::iter::once(&start).chain(path) {
+            if grid.portal_partner(prev) == Some(p) || (wrap && grid.nearest_image(prev, p) != p) {
+                write_polyline(&mut s, &points);
+                points.clear();
+            }
+            points.push(p);
+            prev = p;
+        }
+        write_polyline(&mut s, &points);
+        write_glyph(&mut s, "S", start);
+    }
+    for &end in ends {
+        write_glyph(&mut s, "E", end);
+    }
+    writeln!(s, "</svg>").unwrap();
+    s
+}
+
+fn write_polyline(s: &mut String, points: &[Pos]) {
+    if points.len() < 2 {
+        return;
+    }
+    let points: Vec<String> = points
+        .iter()
+        .map(|Pos(r, c)| format!("{c}.5,{r}.5"))
+        .collect();
+    writeln!(
+        s,
+        r#"<polyline points="{}" fill="none" stroke="rgb(0,228,48)" stroke-width="0.1"/>"#,
+        points.join(" ")
+    )
+    .unwrap();
+}
+
+fn write_glyph(s: &mut String, glyph: &str, Pos(r, c): Pos) {
+    writeln!(
+        s,
+        r#"<text x="{c}.5" y="{r}.5" font-size="0.8" fill="white" text-anchor="middle" dominant-baseline="central">{glyph}</text>"#
+    )
+    .unwrap();
+}
+
+pub fn save_svg(
+    path: &Path,
+    grid: &Grid,
+    wrap: bool,
+    start: Option<Pos>,
+    ends: &[Pos],
+    route: &[Pos],
+) -> std::io::Result<()> {
+    std::fs::write(path, svg(grid, wrap, start, ends, route))
+}
+
+/// Writes an RGBA screen capture as `frame_<index>.png` in `dir`. Captures come bottom row
+/// first, as OpenGL reads them, so the rows are flipped. The numbered frames can be joined
+/// into a GIF with e.g. `ffmpeg -i frame_%05d.png search.gif`.
+pub fn save_frame(
+    dir: &Path,
+    index: u32,
+    width: u32,
+    height: u32,
+    rgba: &[u8],
+) -> std::io::Result<()> {
+    let row_len = width as usize * 4;
+    let flipped: Vec<u8> = rgba
+        .chunks_exact(row_len)
+        .rev()
+        .flatten()
+        .copied()
+        .collect();
+    image::save_buffer(
+        dir.join(format!("frame_{index:05}.png")),
+        &flipped,
+        width,
+        height,
+        image::ColorType::Rgba8,
+    )
+    .map_err(std::io::Error::other)
+}
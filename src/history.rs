@@ -0,0 +1,96 @@
+//! Undo and redo of grid edits.
+//!
+//! An edit is everything that changed between `begin` and `end`, so a whole drag stroke or
+//! generated maze is undone at once.
+
+use crate::pathfind::{Grid, Pos};
+
+/// Terrain of a cell: its cost and the direction it may only be left in.
+type Cell = (Option<u64>, Option<(i64, i64)>);
+
+struct Change {
+    pos: Pos,
+    before: Cell,
+    after: Cell,
+}
+
+#[derive(Default)]
+pub struct History {
+    undo: Vec<Vec<Change>>,
+    redo: Vec<Vec<Change>>,
+    // the grid as it was when the pending edit began
+    before: Option<Grid>,
+}
+
+fn cell(grid: &Grid, pos: Pos) -> Cell {
+    (grid.cost(pos), grid.exit(pos))
+}
+
+fn set_cell(grid: &mut Grid, pos: Pos, (cost, exit): Cell) {
+    grid.set_cost(pos, cost);
+    if exit.is_some() {
+        grid.set_exit(pos, exit);
+    }
+}
+
+impl History {
+    pub fn begin(&mut self, grid: &Grid) {
+        if self.before.is_none() {
+            self.before = Some(grid.clone());
+        }
+    }
+
+    /// Records the cells that changed since `begin` as one edit.
+    pub fn end(&mut self, grid: &Grid) {
+        let Some(before) = self.before.take() else {
+            return;
+        };
+        if before.rows() != grid.rows() || before.cols() != grid.cols() {
+            return;
+        }
+
+        let mut changes = Vec::new();
+        for r in 0..grid.rows() as i64 {
+            for c in 0..grid.cols() as i64 {
+                let pos = Pos(r, c);
+                let (before, after) = (cell(&before, pos), cell(grid, pos));
+                if before != after {
+                    changes.push(Change { pos, before, after });
+                }
+            }
+        }
+        if !changes.is_empty() {
+            self.undo.push(changes);
+            self.redo.clear();
+        }
+    }
+
+    /// Reverts the last edit. Returns `false` if there was nothing to undo.
+    pub fn undo(&mut self, grid: &mut Grid) -> bool {
+        let Some(changes) = self.undo.pop() else {
+            return false;
+        };
+        for change in changes.iter().rev() {
+            set_cell(grid, change.pos, change.before);
+        }
+        self.redo.push(changes);
+        true
+    }
+
+    /// Applies the last undone edit again. Returns `false` if there was nothing to redo.
+    pub fn redo(&mut self, grid: &mut Grid) -> bool {
+        let Some(changes) = self.redo.pop() else {
+            return false;
+        };
+        for change in &changes {
+            set_cell(grid, change.pos, change.after);
+        }
+        self.undo.push(changes);
+        true
+    }
+
+    /// Forgets all edits, for when the grid is replaced.
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+}
@@ -1,27 +1,235 @@
-use std::collections::BinaryHeap;
+mod bench;
+mod export;
+mod history;
+mod map;
+mod maze;
+mod pathfind;
+mod rng;
+mod settings;
+
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
 
 use macroquad::prelude::*;
 
-#[derive(PartialEq, Eq, Copy, Clone)]
-struct Pos(i64, i64);
+use history::History;
+use pathfind::{
+    Algorithm, DStarLite, Grid, Heuristic, OpenList, Options, Pos, SQRT2_DIAGONAL_COST,
+    SQRT2_ORTHOGONAL_COST, Search, TieBreak, Topology,
+};
+use rng::Rng;
+use settings::Settings;
 
-impl Pos {
-    fn distance(&self, other: &Self) -> u64 {
-        self.0.abs_diff(other.0) + self.1.abs_diff(other.1)
-    }
+// terrain tiers selectable as a brush and the cell cost each paints, `None` being walls
+const BRUSHES: [(&str, Option<u64>); 4] = [
+    ("wall", None),
+    ("road", Some(1)),
+    ("grass", Some(2)),
+    ("swamp", Some(5)),
+];
+const BRUSH_KEYS: [KeyCode; 4] = [KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4];
+// costs up to 9 can come from map files, shaded on one scale
+const MAX_TERRAIN_COST: u64 = 9;
+const GRASS_COLOR: Color = Color::new(0.35, 0.7, 0.25, 0.45);
+const SWAMP_COLOR: Color = Color::new(0.3, 0.35, 0.15, 0.75);
+const MAX_BRUSH_SIZE: i64 = 7;
+
+const WALL_COLOR: Color = Color::new(0.9, 0.9, 0.9, 1.00);
+const CLOSED_COLOR: Color = Color::new(0.3, 0.4, 0.9, 0.4);
+const OPEN_COLOR: Color = Color::new(0.3, 0.9, 0.9, 0.4);
+const UNREACHABLE_COLOR: Color = Color::new(0.0, 0.0, 0.0, 0.6);
+const REACHABLE_COLOR: Color = Color::new(0.3, 0.9, 0.3, 0.2);
+const ARROW_COLOR: Color = Color::new(0.9, 0.7, 0.2, 0.8);
+const COMPARE_COLOR: Color = Color::new(0.9, 0.3, 0.7, 0.6);
+const ALTERNATIVE_COLOR: Color = Color::new(0.9, 0.9, 0.3, 0.7);
+const GOAL_RING_COLOR: Color = Color::new(1.0, 0.85, 0.1, 1.0);
+const PORTAL_COLOR: Color = Color::new(0.8, 0.3, 0.9, 0.6);
+
+// order in which the compared algorithm is cycled
+const ALGORITHMS: [Algorithm; 7] = [
+    Algorithm::AStar,
+    Algorithm::Dijkstra,
+    Algorithm::Bfs,
+    Algorithm::Bidirectional,
+    Algorithm::Jps,
+    Algorithm::GreedyBestFirst,
+    Algorithm::ThetaStar,
+];
+
+const MAX_ANIMATION_SPEED: u32 = 1024;
+// expansions per frame while a responsive search runs
+const SEARCH_BUDGET: u32 = 20_000;
+
+// cells per second the walker moves along the path
+const DEFAULT_WALK_SPEED: f32 = 4.0;
+const MIN_WALK_SPEED: f32 = 0.5;
+const MAX_WALK_SPEED: f32 = 64.0;
+const WALKER_COLOR: Color = Color::new(1.0, 0.5, 0.1, 1.0);
+
+// frames averaged for the frame time readout
+const FRAME_TIME_WINDOW: usize = 30;
+
+// on-screen cell size in pixels above which cells show their scores
+const SCORE_TEXT_MIN_CELL_SIZE: f32 = 60.0;
+// length in pixels of the longer side of the minimap
+const MINIMAP_SIZE: f32 = 200.0;
+const MINIMAP_BACKGROUND: Color = Color::new(0.1, 0.1, 0.1, 0.85);
+
+const MIN_ZOOM: f32 = 0.01;
+const MAX_ZOOM: f32 = 1.0;
+// zoom factor of one notch of the mouse wheel
+const ZOOM_STEP: f32 = 1.1;
+// factor each key press stretches or squeezes the cells by, and the limits of either side
+const CELL_SCALE_STEP: f32 = 1.25;
+const MIN_CELL_SCALE: f32 = 0.25;
+const MAX_CELL_SCALE: f32 = 4.0;
+
+// window heights per second that the camera pans while Shift+arrow is held
+const PAN_SPEED: f32 = 0.8;
+
+// cells of the grid that stay in view however far the camera is panned
+const MIN_VISIBLE_CELLS: f32 = 2.0;
+
+// fraction of the window the grid fills after fitting the camera
+const FIT_MARGIN: f32 = 0.9;
+
+const DENSITY_STEP: u32 = 5;
+const MAX_CAVE_ITERATIONS: u32 = 10;
+
+// frames the cursor must rest while drawing before the path is recalculated
+const DRAW_DEBOUNCE_FRAMES: u32 = 5;
+
+const HEURISTIC_WEIGHT_STEP: f64 = 0.25;
+const MAX_HEURISTIC_WEIGHT: f64 = 5.0;
+const MAX_CLEARANCE_WEIGHT: u64 = 10;
+const MAX_CLEARANCE_RADIUS: u64 = 3;
+const MAX_MOVE_COST: u64 = 20;
+// larger grids leave out the lines between cells unless asked for
+const MAX_GRIDLINE_GRID_SIZE: u64 = 100;
+// most routes shown at once, counting the best one
+const MAX_PATH_COUNT: usize = 5;
+
+const GRID_STEP: u64 = 5;
+const MIN_GRID_SIZE: u64 = 5;
+const MAX_GRID_SIZE: u64 = 200;
+
+const DEFAULT_MAP_PATH: &str = "map.txt";
+const PATH_CSV_PATH: &str = "path.csv";
+const SVG_PATH: &str = "grid.svg";
+const ASCII_PATH: &str = "solution.txt";
+
+const KEY_HELP: &[&str] = &[
+    "[F1] hide this help",
+    "[LMB] paint  [RMB] erase  [Shift+drag] rectangle  [Ctrl+drag] line",
+    "[S] set start at next click  [Shift+S] toggle snapping endpoints off walls",
+    "[E] set end at next click  [Shift+E] add/remove end",
+    "[Arrows] nudge start/end  [Tab] switch nudged endpoint  [Shift+arrows] pan",
+    "[1] A*  [2] Dijkstra  [3] BFS",
+    "[4] bidirectional A*  [5] JPS  [6] greedy best-first  [7] Theta*",
+    "[8] D* Lite, replanning after each edit",
+    "[D] cycle 4/8/hex connectivity  [Shift+D] cycle heuristic",
+    "[K] toggle corner cutting  [J] toggle sqrt(2) step costs",
+    "[Q] toggle wrap-around edges",
+    "[+/-] grow/shrink grid  [Shift+ +/-] raise/lower the orthogonal step cost",
+    "[T] cycle brush  [Shift+1-4] wall/road/grass/swamp brush",
+    "[B/Shift+B] grow/shrink brush  [\\] cycle mirror drawing",
+    "[O] toggle open/closed overlay  [L] toggle smoothed path",
+    "[Shift+L] toggle line of sight from start to cursor  [Shift+O] heap/scanned open list",
+    "[H] hold for gscore heatmap  [U] toggle reachable area",
+    "[I] toggle connected regions  [Shift+I] number cells by expansion order",
+    "[Y] toggle distance to walls",
+    "[;/'] heuristic weight  [Shift+;/'] wall clearance penalty  [G] cycle tie-break",
+    "[Shift+G] cycle gridlines: auto/on/off",
+    "[/] cycle algorithm to compare against  [`/Shift+`] wall clearance radius",
+    "[Shift+/] toggle legend of overlay colors",
+    "[V] toggle animation  [Space] pause  [N/Shift+N] step forward/back  [,/.] speed",
+    "[Shift+V] spread searches over frames or finish them at once",
+    "[drag bottom bar] replay a finished animated search",
+    "[Z] toggle walker  [Shift+Z] toggle looping  [Shift+,/.] walker speed",
+    "[Ctrl+S] save map, twice to overwrite  [Ctrl+L] load map",
+    "[Ctrl+E] export path as CSV  [Ctrl+G] export SVG  [Ctrl+A] export ASCII",
+    "[M] generate maze  [Shift+M] cycle maze generator",
+    "[R] random walls  [Shift+R] caves  [[/]] density  [Shift+[/]] cave smoothing",
+    "[W] add/remove waypoint  [P] link/remove portal  [Shift+P] path as line/cells",
+    "[Enter/Shift+Enter] more/fewer alternative routes",
+    "[A] cycle one-way direction",
+    "[C] clear walls  [Shift+C] wall frame  [X] clear start/end/waypoints",
+    "[Shift+X] start and end in opposite corners",
+    "[F] fit grid to window, refitting on resize until panned",
+    "[9/0] narrower/wider cells  [Shift+9/0] shorter/taller cells",
+    "[Ctrl+Z] undo  [Ctrl+Y] redo  [Ctrl+I] invert walls",
+];
+
+struct Args {
+    map_path: PathBuf,
+    load: bool,
+    seed: Option<u64>,
+    // number of headless benchmark iterations
+    bench: Option<u32>,
+    image: Option<PathBuf>,
+    // directory the frames of the animated search are written to
+    record: Option<PathBuf>,
+    // solve a grid read from standard input instead of opening a window
+    stdin: bool,
+    // print the solved grid rather than the path when solving from standard input
+    ascii: bool,
 }
 
-impl std::ops::Add<Pos> for Pos {
-    type Output = Pos;
+impl Args {
+    fn parse() -> Self {
+        let mut args = Args {
+            map_path: PathBuf::from(DEFAULT_MAP_PATH),
+            load: false,
+            seed: None,
+            bench: None,
+            image: None,
+            record: None,
+            stdin: false,
+            ascii: false,
+        };
 
-    fn add(self, rhs: Pos) -> Self::Output {
-        Self(self.0 + rhs.0, self.1 + rhs.1)
+        let mut iter = std::env::args().skip(1);
+        while let Some(arg) = iter.next() {
+            match arg.as_str() {
+                "--map" => {
+                    if let Some(path) = iter.next() {
+                        args.map_path = PathBuf::from(path);
+                    }
+                }
+                "--load" => {
+                    if let Some(path) = iter.next() {
+                        args.map_path = PathBuf::from(path);
+                        args.load = true;
+                    }
+                }
+                "--image" => {
+                    if let Some(path) = iter.next() {
+                        args.image = Some(PathBuf::from(path));
+                    }
+                }
+                "--record" => {
+                    if let Some(path) = iter.next() {
+                        args.record = Some(PathBuf::from(path));
+                    }
+                }
+                "--stdin" => args.stdin = true,
+                "--ascii" => args.ascii = true,
+                "--seed" => match iter.next().map(|seed| seed.parse()) {
+                    Some(Ok(seed)) => args.seed = Some(seed),
+                    _ => eprintln!("--seed expects an unsigned integer"),
+                },
+                "--bench" => match iter.next().map(|iterations| iterations.parse()) {
+                    Some(Ok(iterations)) if iterations > 0 => args.bench = Some(iterations),
+                    _ => eprintln!("--bench expects a positive number of iterations"),
+                },
+                _ => eprintln!("ignoring unknown argument {arg:?}"),
+            }
+        }
+        args
     }
 }
 
-const ROWS: u64 = 20;
-const COLS: u64 = 20;
-
 fn conf() -> miniquad::conf::Conf {
     miniquad::conf::Conf {
         window_title: "Pathfinding!".to_owned(),
@@ -37,353 +245,3156 @@ fn conf() -> miniquad::conf::Conf {
     }
 }
 
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+enum Endpoint {
+    Start,
+    End,
+}
+
 #[derive(Debug, PartialEq)]
 enum ControlState {
     Grid,
+    // the endpoint is set at the next left click
+    Placing(Endpoint),
     Panning,
-    Drawing(bool),
+    // dragging along the replay bar of a finished animated search
+    Scrubbing,
+    Drawing(Option<u64>),
+    Rectangle {
+        anchor: Pos,
+        corner: Pos,
+        paint: Option<u64>,
+    },
+    Line {
+        anchor: Pos,
+        end: Pos,
+        paint: Option<u64>,
+    },
 }
 
-#[derive(PartialEq, Eq)]
-struct CellData {
-    pos: Pos,
-    fscore: u64,
+/// When the lines between cells are drawn.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+enum Gridlines {
+    /// Only on grids no more than `MAX_GRIDLINE_GRID_SIZE` cells on a side.
+    Auto,
+    On,
+    Off,
 }
 
-impl Ord for CellData {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        // min heap
-        other.fscore.cmp(&self.fscore).then_with(|| {
-            other
-                .pos
-                .0
-                .cmp(&self.pos.0)
-                .then_with(|| other.pos.1.cmp(&self.pos.1))
-        })
-    }
+/// How the path is drawn.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+enum PathStyle {
+    /// A line through the centers of the path cells.
+    Line,
+    /// The cells the path passes through, filled.
+    Cells,
+}
+
+/// Mirror images painted along with each cell.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+enum Symmetry {
+    Off,
+    /// Mirrored across the vertical center line.
+    LeftRight,
+    /// Mirrored across the horizontal center line.
+    TopBottom,
+    /// Mirrored across both center lines, into all four quadrants.
+    Quad,
 }
 
-impl PartialOrd for CellData {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(self.cmp(other))
+impl Symmetry {
+    fn next(self) -> Self {
+        match self {
+            Symmetry::Off => Symmetry::LeftRight,
+            Symmetry::LeftRight => Symmetry::TopBottom,
+            Symmetry::TopBottom => Symmetry::Quad,
+            Symmetry::Quad => Symmetry::Off,
+        }
+    }
+
+    /// `pos` followed by its mirror images in a grid of `rows` by `cols`. Cells on a center
+    /// line are their own image and appear twice.
+    fn images(self, rows: u64, cols: u64, pos: Pos) -> Vec<Pos> {
+        let (flip_rows, flip_cols) = match self {
+            Symmetry::Off => (false, false),
+            Symmetry::LeftRight => (false, true),
+            Symmetry::TopBottom => (true, false),
+            Symmetry::Quad => (true, true),
+        };
+        let mirrored_rows = [pos.0, rows as i64 - 1 - pos.0];
+        let mirrored_cols = [pos.1, cols as i64 - 1 - pos.1];
+        let rows = &mirrored_rows[..1 + flip_rows as usize];
+        let cols = &mirrored_cols[..1 + flip_cols as usize];
+        rows.iter()
+            .flat_map(|&r| cols.iter().map(move |&c| Pos(r, c)))
+            .collect()
     }
 }
 
+/// Why a route has no path.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+enum NoPathReason {
+    StartUnset,
+    EndUnset,
+    StartBlocked,
+    EndBlocked,
+    WaypointBlocked,
+    // the search ran out of cells without reaching a stop
+    Disconnected,
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+enum PathStatus {
+    // waiting for the search to start over after an edit
+    Unset,
+    Searching,
+    Found,
+    NoPath(NoPathReason),
+    // a path exists, but not one keeping the wall clearance
+    NoClearPath,
+}
+
+/// Outcome of searching the whole route at once.
+struct Route {
+    path: Option<Vec<Pos>>,
+    cost: u64,
+    numcalc: u64,
+}
+
 struct Context {
     mouse_grid: Option<Pos>,
     control_state: ControlState,
     zoom: f32,
+    // on-screen width and height of a cell relative to the zoom; the world keeps unit cells
+    // and the camera stretches it
+    cell_size: Vec2,
     camera: Camera2D,
-    is_wall: [[bool; COLS as usize]; ROWS as usize],
+    // window size last frame, compared against to notice resizes
+    window_size: Vec2,
+    // panned or zoomed by hand since the grid was last fit, which stops resizes refitting it
+    view_moved: bool,
+    grid: Grid,
+    brush: usize,
+    // side length of the square painted around the cursor
+    brush_size: i64,
+    // percentage of cells turned into walls by random fill
+    fill_density: u32,
+    // smoothing passes of the cave generator
+    cave_iterations: u32,
+    symmetry: Symmetry,
 
     start: Option<Pos>,
-    end: Option<Pos>,
+    // the path leads to whichever of these is nearest
+    ends: Vec<Pos>,
+    // the end the current path reaches
+    goal: Option<Pos>,
     path: Vec<Pos>,
+    path_cost: u64,
+    path_status: PathStatus,
+    // corners of the path after string pulling
+    smoothed_path: Vec<Pos>,
+
+    options: Options,
+    search: Option<Search>,
+    // whether D* Lite updates the previous route instead of searching from scratch
+    replanning: bool,
+    // D* Lite state of each segment of the route
+    replanners: Vec<DStarLite>,
+    // first end of a portal waiting to be linked
+    portal_anchor: Option<Pos>,
+    // cells the path must pass through in order between start and end
+    waypoints: Vec<Pos>,
+    history: History,
+    // index of the stop the current search starts from
+    segment: usize,
+    // expansions of the segments searched before the current one
+    segment_numcalc: u64,
+    // set by edits so that the search runs once after input handling
+    dirty: bool,
+    // frames since the last cell painted in the current stroke
+    frames_since_paint: u32,
+    show_search: bool,
+    show_smoothed: bool,
+    path_style: PathStyle,
+    gridlines: Gridlines,
+    // the line-of-sight check from the start to the cursor, as Theta* traces it when it is the
+    // algorithm and as smoothing does otherwise
+    show_sight: bool,
+    // whether the arrow keys move the last end rather than the start
+    nudge_end: bool,
+    // whether holding S or E drags the endpoint instead of placing it with a click
+    hold_to_place: bool,
+    // whether an endpoint placed on a wall moves to the nearest open cell
+    snap_to_open: bool,
+    show_reachable: bool,
+    // cells reachable from the start, indexed like the grid; empty when not shown
+    reachable: Vec<bool>,
+    show_components: bool,
+    // numbers closed cells by when they were expanded, when zoomed in far enough for text
+    show_order: bool,
+    // region of each cell and the number of regions, computed when first drawn after an edit
+    components: Option<(Vec<Option<usize>>, usize)>,
+    show_wall_distances: bool,
+    // a box naming the colors of the overlays currently drawn
+    show_legend: bool,
+    // the key help, drawn in a column right of the stats
+    show_help: bool,
+    // distance from each cell to the nearest wall and the largest one, computed when first
+    // drawn after an edit
+    wall_distances: Option<(Vec<Option<u64>>, u64)>,
+    animate: bool,
+    // the animation setting to save while --record has turned animation on in its place
+    animate_setting: Option<bool>,
+    paused: bool,
+    // searches without animation spread over frames, SEARCH_BUDGET expansions at a time,
+    // instead of finishing within the frame that started them
+    responsive: bool,
+    // expansions per frame while animating
+    speed: u32,
+    // expansions since the route was last searched from the beginning
+    steps_taken: u32,
+    // expansions the last animated search took to finish, the span of the replay bar
+    replay_steps: Option<u32>,
+    show_walker: bool,
+    walk_loop: bool,
+    // how far the walker has moved along the path, in cells
+    walk_distance: f32,
+    walk_speed: f32,
+
+    rng: Rng,
+    maze_generator: maze::Generator,
+    // seed the last generated map was made from, shown so that it can be replayed with --seed
+    map_seed: u64,
+
+    map_path: PathBuf,
+    // set when a save found the map file already there; saving again overwrites it
+    overwrite_pending: bool,
+    message: Option<String>,
+    // directory frames are recorded to until the animated search finishes
+    record_dir: Option<PathBuf>,
+    recorded_frames: u32,
 
     stat_numcalc: u64,
+    // most cells open at once in any segment of the route
+    stat_open_max: usize,
+    // open cells after each animated expansion, for the sparkline
+    open_history: Vec<usize>,
+    // durations of the latest frames in seconds, oldest first
+    frame_times: VecDeque<f32>,
+    // time spent searching, summed over animation frames
+    stat_time_ms: f64,
+    // cost of the A* path, computed to judge greedy best-first results and kept while the
+    // same search is replayed
+    stat_optimal_cost: Option<u64>,
+    // expansions A* needs from scratch for the route D* Lite replanned
+    stat_full_numcalc: Option<u64>,
+    // second algorithm searched on the same route, with its outcome
+    compare_algorithm: Option<Algorithm>,
+    comparison: Option<Route>,
+    // number of cheapest distinct routes searched, 1 for just the best
+    path_count: usize,
+    // the cheapest routes with their costs, best first, when more than one is asked for
+    alternatives: Vec<(Vec<Pos>, u64)>,
 }
 
 impl Context {
     fn set_control_state(&mut self, control_state: ControlState) {
         if self.control_state != control_state {
+            // a drag stroke is undone as a whole
+            if matches!(control_state, ControlState::Drawing(_)) {
+                self.history.begin(&self.grid);
+            } else if matches!(self.control_state, ControlState::Drawing(_)) {
+                self.history.end(&self.grid);
+            }
             self.control_state = control_state;
         }
     }
 
-    fn is_passable(&self, pos: Pos) -> bool {
-        pos.0 >= 0
-            && pos.0 < ROWS as i64
-            && pos.1 >= 0
-            && pos.1 < COLS as i64
-            && !self.is_wall[pos.0 as usize][pos.1 as usize]
+    fn grid_extent(&self) -> Vec2 {
+        grid_extent(self.options.topology, self.grid.rows(), self.grid.cols())
     }
 
-    fn calculate(&mut self) {
-        self.stat_numcalc = 0;
-        if let (Some(start), Some(end)) = (self.start, self.end) {
-            self.path = Vec::new();
-
-            // A* algorithm
-            let mut q: BinaryHeap<CellData> = BinaryHeap::new();
+    /// Centers the camera on the grid and zooms so that all of it is visible.
+    fn fit_camera(&mut self) {
+        let size = self.grid_extent() * self.cell_size;
+        let aspect = screen_width() / screen_height();
+        self.zoom =
+            (FIT_MARGIN * f32::min(2.0 / size.y, 2.0 * aspect / size.x)).clamp(MIN_ZOOM, MAX_ZOOM);
+        self.camera.target = self.grid_extent() / 2.0;
+        self.view_moved = false;
+    }
 
-            q.push(CellData {
-                pos: start,
-                fscore: start.distance(&end),
-            });
+    /// Camera zoom for the current zoom level, cell size and window shape.
+    fn camera_zoom(&self) -> Vec2 {
+        vec2(self.zoom * screen_height() / screen_width(), self.zoom) * self.cell_size
+    }
 
-            if !(self.is_passable(start) && self.is_passable(end)) {
-                return;
-            }
+    /// Half the width and height of the world in view, which the camera zoom maps to 1.
+    fn half_view(&self) -> Vec2 {
+        vec2(1.0, 1.0) / self.camera.zoom.abs()
+    }
 
-            let mut gscore: [[Option<u64>; COLS as usize]; ROWS as usize] =
-                [[None; COLS as usize]; ROWS as usize];
-            gscore[start.0 as usize][start.1 as usize] = Some(0);
-            let mut parent = [[None; COLS as usize]; ROWS as usize];
-            let mut visited = [[false; COLS as usize]; ROWS as usize];
-            while !q.is_empty() {
-                let curr = q.pop().unwrap().pos;
-                if visited[curr.0 as usize][curr.1 as usize] {
-                    continue;
-                }
+    /// Moves the camera back just far enough that part of the grid is in view.
+    fn clamp_camera(&mut self) {
+        let grid_size = self.grid_extent();
+        let half_view = self.half_view();
+        let margin = grid_size.min(Vec2::splat(MIN_VISIBLE_CELLS));
+        self.camera.target = self
+            .camera
+            .target
+            .clamp(margin - half_view, grid_size - margin + half_view);
+    }
 
-                self.stat_numcalc += 1;
-                if curr == end {
-                    // reconstruct path
-                    let mut p = end;
-                    while p != start {
-                        self.path.push(p);
-                        p = parent[p.0 as usize][p.1 as usize].unwrap();
-                    }
+    fn set_algorithm(&mut self, algorithm: Algorithm) {
+        if self.options.algorithm != algorithm || self.replanning {
+            self.options.algorithm = algorithm;
+            self.replanning = false;
+            self.calculate();
+        }
+    }
 
-                    self.path.reverse();
-                    break;
-                }
+    fn start_replanning(&mut self) {
+        if !self.replanning {
+            self.replanning = true;
+            self.replanners.clear();
+            self.calculate();
+        }
+    }
 
-                for direction in [(-1, 0), (1, 0), (0, 1), (0, -1)] {
-                    let next_pos = curr + Pos(direction.0, direction.1);
-                    if self.is_passable(next_pos)
-                        && parent[next_pos.0 as usize][next_pos.1 as usize].is_none()
-                    {
-                        parent[next_pos.0 as usize][next_pos.1 as usize] = Some(curr);
-
-                        let tentative_gscore =
-                            gscore[curr.0 as usize][curr.1 as usize].unwrap() + 1;
-                        let next_gscore = gscore[next_pos.0 as usize][next_pos.1 as usize];
-                        if next_gscore.is_none() || tentative_gscore < next_gscore.unwrap() {
-                            gscore[next_pos.0 as usize][next_pos.1 as usize] =
-                                Some(tentative_gscore);
-
-                            q.push(CellData {
-                                pos: next_pos,
-                                fscore: tentative_gscore + next_pos.distance(&end),
-                            });
-                            visited[next_pos.0 as usize][next_pos.1 as usize] = false;
-                        }
-                    }
-                }
+    fn set_topology(&mut self, topology: Topology) {
+        if self.options.topology != topology {
+            self.options.topology = topology;
+            let algorithm = self.options.algorithm;
+            // jump point search assumes 8-connectivity, and Theta* square cells
+            if (algorithm == Algorithm::Jps && topology != Topology::Square8)
+                || (algorithm == Algorithm::ThetaStar && topology == Topology::Hex)
+            {
+                self.options.algorithm = Algorithm::AStar;
             }
+            self.calculate();
+        }
+    }
+
+    fn set_corner_cutting(&mut self, corner_cutting: bool) {
+        if self.options.corner_cutting != corner_cutting {
+            self.options.corner_cutting = corner_cutting;
+            self.calculate();
+        }
+    }
+
+    /// Switches between unit orthogonal steps costing half a diagonal and the scaled costs
+    /// that approximate sqrt(2).
+    fn set_sqrt2_costs(&mut self, sqrt2_costs: bool) {
+        let (orthogonal_cost, diagonal_cost) = if sqrt2_costs {
+            (SQRT2_ORTHOGONAL_COST, SQRT2_DIAGONAL_COST)
         } else {
-            self.path = Vec::new();
+            (
+                Options::default().orthogonal_cost,
+                Options::default().diagonal_cost,
+            )
+        };
+        if (self.options.orthogonal_cost, self.options.diagonal_cost)
+            != (orthogonal_cost, diagonal_cost)
+        {
+            self.options.orthogonal_cost = orthogonal_cost;
+            self.options.diagonal_cost = diagonal_cost;
+            self.calculate();
         }
     }
-}
 
-pub(crate) fn draw_text_centered(
-    text: &str,
-    x: f32,
-    y: f32,
-    font_size: f32,
-    font_scale: f32,
-    color: Color,
-) {
-    let center = get_text_center(text, None, font_size as u16, font_scale, 0.0);
-    draw_text_ex(
-        text,
-        x - center.x,
-        y - center.y,
-        TextParams {
-            font_size: font_size as u16,
-            font_scale,
-            color,
-            ..Default::default()
-        },
-    );
-}
+    /// Sets the cost of an orthogonal step. Diagonals keep costing either two such steps or,
+    /// if they were cheaper than that, about sqrt(2) of one.
+    fn set_move_cost(&mut self, orthogonal_cost: u64) {
+        let orthogonal_cost = orthogonal_cost.clamp(1, MAX_MOVE_COST);
+        let Options {
+            orthogonal_cost: old_orthogonal_cost,
+            diagonal_cost: old_diagonal_cost,
+            ..
+        } = self.options;
+        if old_orthogonal_cost == orthogonal_cost {
+            return;
+        }
+        self.options.orthogonal_cost = orthogonal_cost;
+        // rounding must not make a diagonal as cheap as a single orthogonal step
+        self.options.diagonal_cost = if old_diagonal_cost < 2 * old_orthogonal_cost {
+            ((orthogonal_cost as f64 * std::f64::consts::SQRT_2).round() as u64)
+                .max(orthogonal_cost + 1)
+        } else {
+            2 * orthogonal_cost
+        };
+        self.calculate();
+    }
 
-#[macroquad::main(conf)]
-async fn main() {
-    clear_background(BLACK);
+    fn set_heuristic_weight(&mut self, heuristic_weight: f64) {
+        let heuristic_weight = heuristic_weight.clamp(1.0, MAX_HEURISTIC_WEIGHT);
+        if self.options.heuristic_weight != heuristic_weight {
+            self.options.heuristic_weight = heuristic_weight;
+            self.calculate();
+        }
+    }
 
-    let mut context = Context {
-        mouse_grid: None,
-        control_state: ControlState::Grid,
-        zoom: 0.1,
-        camera: Camera2D {
-            zoom: vec2(0.1 * screen_height() / screen_width(), 0.1),
-            target: vec2(COLS as f32 / 2.0, ROWS as f32 / 2.0),
-            offset: vec2(0.0, 0.0),
-            ..Default::default()
-        },
-        is_wall: [[false; COLS as usize]; ROWS as usize],
-        start: None,
-        end: None,
-        path: Vec::new(),
+    fn set_heuristic(&mut self, heuristic: Heuristic) {
+        if self.options.heuristic != heuristic {
+            self.options.heuristic = heuristic;
+            self.calculate();
+        }
+    }
 
-        stat_numcalc: 0,
-    };
+    fn set_clearance_weight(&mut self, clearance_weight: u64) {
+        let clearance_weight = clearance_weight.min(MAX_CLEARANCE_WEIGHT);
+        if self.options.clearance_weight != clearance_weight {
+            self.options.clearance_weight = clearance_weight;
+            self.calculate();
+        }
+    }
 
-    loop {
-        if is_key_pressed(KeyCode::Escape) {
+    fn set_clearance_radius(&mut self, clearance_radius: u64) {
+        let clearance_radius = clearance_radius.min(MAX_CLEARANCE_RADIUS);
+        if self.options.clearance_radius != clearance_radius {
+            self.options.clearance_radius = clearance_radius;
+            self.calculate();
+        }
+    }
+
+    fn set_tie_break(&mut self, tie_break: TieBreak) {
+        if self.options.tie_break != tie_break {
+            self.options.tie_break = tie_break;
+            self.calculate();
+        }
+    }
+
+    fn set_open_list(&mut self, open_list: OpenList) {
+        if self.options.open_list != open_list {
+            self.options.open_list = open_list;
+            self.calculate();
+        }
+    }
+
+    fn set_wrap(&mut self, wrap: bool) {
+        if self.options.wrap != wrap {
+            self.options.wrap = wrap;
+            self.calculate();
+        }
+    }
+
+    fn resize_grid(&mut self, rows: u64, cols: u64) {
+        let rows = rows.clamp(MIN_GRID_SIZE, MAX_GRID_SIZE);
+        let cols = cols.clamp(MIN_GRID_SIZE, MAX_GRID_SIZE);
+        if rows == self.grid.rows() && cols == self.grid.cols() {
             return;
         }
 
-        let mouse_wheel_y = mouse_wheel().1;
-        if mouse_wheel_y > 0.0 {
-            context.zoom = f32::max(0.01, context.zoom * 1.1);
-        } else if mouse_wheel_y < 0.0 {
-            context.zoom = f32::min(1.0, context.zoom * 0.9);
-        }
-        context.camera.zoom = vec2(
-            context.zoom * screen_height() / screen_width(),
-            context.zoom,
+        self.grid = self.grid.resized(rows, cols);
+        self.history.clear();
+        let clamp = |p: Pos| Pos(p.0.min(rows as i64 - 1), p.1.min(cols as i64 - 1));
+        self.start = self.start.map(clamp);
+        self.ends = self.ends.iter().copied().map(clamp).collect();
+        self.waypoints = self.waypoints.iter().copied().map(clamp).collect();
+        self.portal_anchor = None;
+        self.calculate();
+    }
+
+    fn set_animate(&mut self, animate: bool) {
+        if self.animate != animate {
+            self.animate = animate;
+            self.animate_setting = None;
+            self.calculate();
+        }
+    }
+
+    fn set_responsive(&mut self, responsive: bool) {
+        if self.responsive != responsive {
+            self.responsive = responsive;
+            self.calculate();
+        }
+    }
+
+    /// Writes the frame just drawn while the search runs, and the first one after it ends.
+    fn record_frame(&mut self) {
+        let Some(dir) = &self.record_dir else {
+            return;
+        };
+        let searching = self.path_status == PathStatus::Searching;
+        if !searching && self.recorded_frames == 0 {
+            return;
+        }
+
+        // each frame goes straight to disk, so a long search never piles up in memory
+        let screen = get_screen_data();
+        let saved = export::save_frame(
+            dir,
+            self.recorded_frames,
+            screen.width as u32,
+            screen.height as u32,
+            &screen.bytes,
         );
+        if let Err(err) = saved {
+            self.message = Some(format!("recording failed: {err}"));
+            self.record_dir = None;
+            return;
+        }
+        self.recorded_frames += 1;
+        if !searching {
+            self.message = Some(format!(
+                "recorded {} frames to {}",
+                self.recorded_frames,
+                dir.display()
+            ));
+            self.record_dir = None;
+        }
+    }
 
-        let mouse_pos_world = context.camera.screen_to_world(mouse_position().into());
-        context.mouse_grid = if mouse_pos_world.x >= 0.0
-            && mouse_pos_world.x < COLS as f32
-            && mouse_pos_world.y >= 0.0
-            && mouse_pos_world.y < ROWS as f32
-        {
-            Some(Pos(mouse_pos_world.y as i64, mouse_pos_world.x as i64))
-        } else {
-            None
+    fn save_settings(&self) {
+        let settings = Settings {
+            zoom: self.zoom,
+            cell_w: self.cell_size.x,
+            cell_h: self.cell_size.y,
+            rows: self.grid.rows(),
+            cols: self.grid.cols(),
+            options: self.options,
+            animate: self.animate_setting.unwrap_or(self.animate),
+            responsive: self.responsive,
+            speed: self.speed,
+            hold_to_place: self.hold_to_place,
+            snap_to_open: self.snap_to_open,
         };
+        if let Err(err) = settings::save(&settings) {
+            eprintln!("failed to save settings: {err}");
+        }
+    }
 
-        match context.control_state {
-            ControlState::Grid => 'l: {
-                if is_mouse_button_pressed(MouseButton::Middle) {
-                    context.set_control_state(ControlState::Panning);
-                    break 'l;
-                }
+    /// Saves to the map file. If the file already exists, the first save only asks for a second
+    /// one, which then overwrites it unless the map changes in between.
+    fn save_map(&mut self) {
+        if self.map_path.exists() && !self.overwrite_pending {
+            // the status line asks for the second save until then
+            self.overwrite_pending = true;
+            return;
+        }
+        self.overwrite_pending = false;
+        self.message = Some(
+            match map::save(&self.map_path, &self.grid, self.start, &self.ends) {
+                Ok(()) => format!("saved {}", self.map_path.display()),
+                Err(err) => format!("failed to save {}: {err}", self.map_path.display()),
+            },
+        );
+    }
 
-                if let Some(Pos(r, c)) = context.mouse_grid
-                    && is_mouse_button_pressed(MouseButton::Left)
-                {
-                    context.set_control_state(ControlState::Drawing(
-                        !context.is_wall[r as usize][c as usize],
-                    ));
-                    break 'l;
-                }
+    fn export_path(&mut self) {
+        let route = match (self.path_status, self.start) {
+            (PathStatus::Found, Some(start)) => Some((start, self.path.as_slice(), self.path_cost)),
+            _ => None,
+        };
+        self.message = Some(
+            match export::save_path_csv(Path::new(PATH_CSV_PATH), route) {
+                Ok(()) => format!("exported path to {PATH_CSV_PATH}"),
+                Err(err) => format!("failed to export {PATH_CSV_PATH}: {err}"),
+            },
+        );
+    }
 
-                if is_key_down(KeyCode::S) {
-                    if context.mouse_grid != context.start {
-                        context.start = context.mouse_grid;
-                        context.calculate();
-                    }
-                }
-                if is_key_down(KeyCode::E) {
-                    if context.mouse_grid != context.end {
-                        context.end = context.mouse_grid;
-                        context.calculate();
-                    }
-                }
-            }
-            ControlState::Panning => 'l: {
-                if is_mouse_button_released(MouseButton::Middle) {
-                    context.set_control_state(ControlState::Grid);
-                    break 'l;
-                }
+    fn export_svg(&mut self) {
+        let path = if self.path_status == PathStatus::Found {
+            self.path.as_slice()
+        } else {
+            &[]
+        };
+        let result = export::save_svg(
+            Path::new(SVG_PATH),
+            &self.grid,
+            self.options.wrap,
+            self.start,
+            &self.ends,
+            path,
+        );
+        self.message = Some(match result {
+            Ok(()) => format!("exported grid to {SVG_PATH}"),
+            Err(err) => format!("failed to export {SVG_PATH}: {err}"),
+        });
+    }
 
-                let delta = mouse_delta_position() / context.camera.zoom;
-                context.camera.target += delta;
+    fn export_ascii(&mut self) {
+        let path = if self.path_status == PathStatus::Found {
+            self.path.as_slice()
+        } else {
+            &[]
+        };
+        let result = export::save_ascii(
+            Path::new(ASCII_PATH),
+            &self.grid,
+            self.start,
+            &self.ends,
+            path,
+        );
+        self.message = Some(match result {
+            Ok(()) => format!("exported grid to {ASCII_PATH}"),
+            Err(err) => format!("failed to export {ASCII_PATH}: {err}"),
+        });
+    }
+
+    fn load_image(&mut self, path: &Path) {
+        match map::load_image(path, MAX_GRID_SIZE) {
+            Ok(map) => {
+                self.grid = map.grid;
+                self.history.clear();
+                self.start = map.start;
+                self.ends = map.ends;
+                self.waypoints.clear();
+                self.portal_anchor = None;
+                self.message = Some(format!("loaded {}", path.display()));
+                self.fit_camera();
+                self.calculate();
             }
-            ControlState::Drawing(is_draw) => 'l: {
-                if is_mouse_button_released(MouseButton::Left) {
-                    context.set_control_state(ControlState::Grid);
-                    break 'l;
-                }
+            Err(err) => {
+                self.message = Some(format!("failed to load {}: {err}", path.display()));
+            }
+        }
+    }
 
-                if let Some(Pos(r, c)) = context.mouse_grid {
-                    if context.is_wall[r as usize][c as usize] != is_draw {
-                        context.is_wall[r as usize][c as usize] = is_draw;
-                        context.calculate()
-                    }
-                }
+    fn load_map(&mut self) {
+        match map::load(&self.map_path) {
+            Ok(map) => {
+                self.grid = map.grid;
+                self.history.clear();
+                self.start = map.start;
+                self.ends = map.ends;
+                self.waypoints.clear();
+                self.portal_anchor = None;
+                self.message = Some(format!("loaded {}", self.map_path.display()));
+                self.calculate();
+            }
+            Err(err) => {
+                self.message = Some(format!("failed to load {}: {err}", self.map_path.display()));
             }
         }
+    }
 
-        set_camera(&context.camera);
+    fn undo(&mut self) {
+        if self.history.undo(&mut self.grid) {
+            self.calculate();
+        } else {
+            self.message = Some("nothing to undo".to_string());
+        }
+    }
 
-        for r in 0..ROWS as i64 {
-            for c in 0..COLS as i64 {
-                if context.is_wall[r as usize][c as usize] {
-                    draw_rectangle(
-                        c as f32,
-                        r as f32,
-                        1.0,
-                        1.0,
-                        Color::new(0.9, 0.9, 0.9, 1.00),
-                    );
-                }
-                draw_rectangle_lines(c as f32, r as f32, 1.0, 1.0, 0.05, WHITE);
+    fn redo(&mut self) {
+        if self.history.redo(&mut self.grid) {
+            self.calculate();
+        } else {
+            self.message = Some("nothing to redo".to_string());
+        }
+    }
 
-                // outline
-                if context.mouse_grid == Some(Pos(r, c)) {
-                    draw_rectangle_lines(c as f32, r as f32, 1.0, 1.0, 0.1, YELLOW);
-                }
-            }
+    /// Moves the selected endpoint one cell in `direction`, skipping over walls. Stays put if
+    /// there is no open cell that way before the edge of the grid.
+    fn nudge_endpoint(&mut self, direction: Pos) {
+        let endpoint = if self.nudge_end {
+            self.ends.last_mut()
+        } else {
+            self.start.as_mut()
+        };
+        let Some(endpoint) = endpoint else {
+            return;
+        };
+
+        let mut next = *endpoint + direction;
+        while self.grid.contains(next) && self.grid.is_wall(next) {
+            next = next + direction;
+        }
+        if self.grid.contains(next) {
+            *endpoint = next;
+            self.calculate();
         }
+    }
 
-        if let Some(start) = context.start {
-            draw_text_centered(
-                "S",
-                start.1 as f32 + 0.5,
-                start.0 as f32 + 0.5,
-                50.0,
-                0.02,
-                WHITE,
-            );
+    fn toggle_waypoint(&mut self, pos: Pos) {
+        if let Some(i) = self.waypoints.iter().position(|&waypoint| waypoint == pos) {
+            self.waypoints.remove(i);
+        } else {
+            self.waypoints.push(pos);
+        }
+        self.calculate();
+    }
 
-            let mut prev_point = start;
-            for p in context.path.iter() {
-                let p1 = vec2(prev_point.1 as f32 + 0.5, prev_point.0 as f32 + 0.5);
-                let p2 = vec2(p.1 as f32 + 0.5, p.0 as f32 + 0.5);
-                draw_line(p1.x, p1.y, p2.x, p2.y, 0.1, GREEN);
-                prev_point = *p;
-            }
-        }
-        if let Some(end) = context.end {
-            draw_text_centered(
-                "E",
-                end.1 as f32 + 0.5,
-                end.0 as f32 + 0.5,
-                50.0,
-                0.02,
-                WHITE,
-            );
+    /// Where an endpoint placed at `pos` goes: the nearest open cell if snapping is on and
+    /// `pos` is a wall.
+    fn snap(&self, pos: Pos) -> Pos {
+        if !self.snap_to_open || !self.grid.is_wall(pos) {
+            return pos;
         }
+        self.options
+            .nearest_passable(&self.grid, pos)
+            .unwrap_or(pos)
+    }
 
-        draw_circle(0.0, 0.0, 0.1, RED);
-        draw_circle(mouse_pos_world.x, mouse_pos_world.y, 0.1, BLUE);
+    /// Tells on the message line that an endpoint aimed at `cursor` was snapped to `pos`.
+    fn report_snap(&mut self, cursor: Option<Pos>, pos: Option<Pos>) {
+        if cursor != pos
+            && let Some(pos) = pos
+        {
+            self.message = Some(format!(
+                "moved onto the nearest open cell {}",
+                format_cell(Some(pos), "")
+            ));
+        }
+    }
 
-        // UI
-        set_default_camera();
-        draw_text(
-            &format!("{:?}", context.control_state),
-            10.0,
-            20.0,
-            20.0,
-            WHITE,
-        );
-        draw_text(
-            &format!("pathlen: {:?}", context.path.len()),
-            10.0,
-            60.0,
-            20.0,
-            WHITE,
-        );
-        draw_text(
-            &format!("numcalc: {:?}", context.stat_numcalc),
-            10.0,
-            80.0,
-            20.0,
-            WHITE,
+    fn toggle_end(&mut self, cursor: Pos) {
+        let pos = self.snap(cursor);
+        self.report_snap(Some(cursor), Some(pos));
+        if let Some(i) = self.ends.iter().position(|&end| end == pos) {
+            self.ends.remove(i);
+        } else {
+            self.ends.push(pos);
+        }
+        self.calculate();
+    }
+
+    /// Removes the portal at `pos`, or links it to the previously chosen cell.
+    fn toggle_portal(&mut self, pos: Pos) {
+        if self.grid.portal_partner(pos).is_some() {
+            self.grid.remove_portal(pos);
+            self.calculate();
+            return;
+        }
+
+        match self.portal_anchor.take() {
+            None => self.portal_anchor = Some(pos),
+            Some(anchor) if anchor == pos => {}
+            Some(anchor) => {
+                self.grid.add_portal(anchor, pos);
+                self.calculate();
+            }
+        }
+    }
+
+    /// Turns the allowed exit of `pos` clockwise, going back to unconstrained after left.
+    fn cycle_exit(&mut self, pos: Pos) {
+        let exit = match self.grid.exit(pos) {
+            None => Some((-1, 0)),
+            Some((-1, 0)) => Some((0, 1)),
+            Some((0, 1)) => Some((1, 0)),
+            Some((1, 0)) => Some((0, -1)),
+            Some(_) => None,
+        };
+        self.history.begin(&self.grid);
+        self.grid.set_exit(pos, exit);
+        self.history.end(&self.grid);
+        self.calculate();
+    }
+
+    /// Top-left and bottom-right cells of the brush around `pos`, clipped to the grid.
+    fn brush_area(&self, pos: Pos) -> (Pos, Pos) {
+        let (rows, cols) = (self.grid.rows() as i64, self.grid.cols() as i64);
+        let (before, after) = ((self.brush_size - 1) / 2, self.brush_size / 2);
+        (
+            Pos((pos.0 - before).max(0), (pos.1 - before).max(0)),
+            Pos((pos.0 + after).min(rows - 1), (pos.1 + after).min(cols - 1)),
+        )
+    }
+
+    fn fill_rectangle(&mut self, anchor: Pos, corner: Pos, paint: Option<u64>) {
+        self.history.begin(&self.grid);
+        let (rows, cols) = (self.grid.rows(), self.grid.cols());
+        for r in anchor.0.min(corner.0)..=anchor.0.max(corner.0) {
+            for c in anchor.1.min(corner.1)..=anchor.1.max(corner.1) {
+                for pos in self.symmetry.images(rows, cols, Pos(r, c)) {
+                    self.grid.set_cost(pos, paint);
+                }
+            }
+        }
+        self.history.end(&self.grid);
+        self.calculate();
+    }
+
+    /// Paints the cells on the straight line from `from` to `to`.
+    fn fill_line(&mut self, from: Pos, to: Pos, paint: Option<u64>) {
+        self.history.begin(&self.grid);
+        let (rows, cols) = (self.grid.rows(), self.grid.cols());
+        for pos in pathfind::bresenham(from, to) {
+            for pos in self.symmetry.images(rows, cols, pos) {
+                self.grid.set_cost(pos, paint);
+            }
+        }
+        self.history.end(&self.grid);
+        self.calculate();
+    }
+
+    fn generate_maze(&mut self) {
+        self.history.begin(&self.grid);
+        self.map_seed = self.rng.seed();
+        let (start, end) = self.maze_generator.generate(&mut self.grid, &mut self.rng);
+        self.history.end(&self.grid);
+        self.start = Some(start);
+        self.ends = vec![end];
+        self.calculate();
+    }
+
+    fn random_fill(&mut self) {
+        let keep: Vec<Pos> = self
+            .start
+            .iter()
+            .chain(&self.ends)
+            .chain(&self.waypoints)
+            .copied()
+            .collect();
+        self.history.begin(&self.grid);
+        self.map_seed = self.rng.seed();
+        maze::random_fill(&mut self.grid, &mut self.rng, self.fill_density, &keep);
+        self.history.end(&self.grid);
+        self.calculate();
+    }
+
+    /// Generates caves, moving the endpoints and waypoints off the new walls.
+    fn generate_cave(&mut self) {
+        self.history.begin(&self.grid);
+        self.map_seed = self.rng.seed();
+        maze::cave(
+            &mut self.grid,
+            &mut self.rng,
+            self.fill_density,
+            self.cave_iterations,
         );
+        self.history.end(&self.grid);
+        self.move_stops_off_walls();
+        self.calculate();
+    }
+
+    /// Walls off the border of the grid, as mazes usually have.
+    fn add_wall_frame(&mut self) {
+        self.history.begin(&self.grid);
+        maze::wall_frame(&mut self.grid);
+        self.history.end(&self.grid);
+        self.move_stops_off_walls();
+        self.calculate();
+    }
+
+    /// Swaps walls and open ground. The endpoints stay where they are, so the status tells
+    /// when they end up on walls.
+    fn invert_walls(&mut self) {
+        self.history.begin(&self.grid);
+        self.grid.invert();
+        self.history.end(&self.grid);
+        self.calculate();
+    }
+
+    /// Moves the endpoints and waypoints that are now walls to the nearest open cells.
+    fn move_stops_off_walls(&mut self) {
+        let (grid, options) = (&self.grid, &self.options);
+        self.start = self
+            .start
+            .and_then(|start| options.nearest_passable(grid, start));
+        let mut ends: Vec<Pos> = Vec::new();
+        for end in self
+            .ends
+            .iter()
+            .filter_map(|&end| options.nearest_passable(grid, end))
+        {
+            if !ends.contains(&end) {
+                ends.push(end);
+            }
+        }
+        self.ends = ends;
+        self.waypoints = self
+            .waypoints
+            .iter()
+            .filter_map(|&waypoint| options.nearest_passable(grid, waypoint))
+            .collect();
+    }
+
+    /// Puts the start in the top-left corner and a single end in the bottom-right one, each
+    /// moved to the nearest open cell if its corner is a wall.
+    fn place_in_corners(&mut self) {
+        let corner = Pos(self.grid.rows() as i64 - 1, self.grid.cols() as i64 - 1);
+        self.start = self.options.nearest_passable(&self.grid, Pos(0, 0));
+        self.ends = self
+            .options
+            .nearest_passable(&self.grid, corner)
+            .into_iter()
+            .collect();
+        self.calculate();
+    }
+
+    /// Where segment `segment` of the route starts and the cells it may end at. The route
+    /// runs from `start` through the waypoints in order, then on to the nearest end.
+    fn segment_stops(&self, segment: usize) -> Option<(Pos, Vec<Pos>)> {
+        let start = self.start?;
+        if self.ends.is_empty() || segment > self.waypoints.len() {
+            return None;
+        }
+
+        let from = if segment == 0 {
+            start
+        } else {
+            self.waypoints[segment - 1]
+        };
+        let to = match self.waypoints.get(segment) {
+            Some(&waypoint) => vec![waypoint],
+            None => self.ends.clone(),
+        };
+        Some((from, to))
+    }
+
+    /// Marks the route stale after an edit. A search still animating against the old grid is
+    /// dropped at once instead of stepping on until the recalculation.
+    fn mark_dirty(&mut self) {
+        self.dirty = true;
+        if self.path_status == PathStatus::Searching {
+            self.search = None;
+            self.path_status = PathStatus::Unset;
+        }
+    }
+
+    fn calculate(&mut self) {
+        self.overwrite_pending = false;
+        self.update_reachable();
+        self.components = None;
+        self.wall_distances = None;
+        self.dirty = false;
+        self.stat_optimal_cost = None;
+        self.restart_search();
+        if !self.animate {
+            self.advance(if self.responsive {
+                SEARCH_BUDGET
+            } else {
+                u32::MAX
+            });
+        }
+        self.publish_result();
+        self.set_compare_algorithm(self.compare_algorithm);
+        self.update_alternatives();
+    }
+
+    /// Starts the route over from its first segment, leaving alone the overlays that only
+    /// depend on the grid and options.
+    fn restart_search(&mut self) {
+        self.stat_numcalc = 0;
+        self.stat_open_max = 0;
+        self.open_history.clear();
+        self.stat_time_ms = 0.0;
+        self.stat_full_numcalc = None;
+        self.path = Vec::new();
+        self.path_cost = 0;
+        self.path_status = PathStatus::Unset;
+        self.smoothed_path = Vec::new();
+        self.walk_distance = 0.0;
+        self.goal = None;
+        self.segment = 0;
+        self.segment_numcalc = 0;
+        self.steps_taken = 0;
+        self.replay_steps = None;
+        if let Some(reason) = self.missing_stop() {
+            self.search = None;
+            self.path_status = PathStatus::NoPath(reason);
+        } else if self.replanning {
+            self.search = None;
+            self.replan();
+        } else {
+            self.start_segment();
+        }
+    }
+
+    /// Why the route cannot be searched at all, if a stop is unset or on a wall.
+    fn missing_stop(&self) -> Option<NoPathReason> {
+        let Some(start) = self.start else {
+            return Some(NoPathReason::StartUnset);
+        };
+        if self.ends.is_empty() {
+            Some(NoPathReason::EndUnset)
+        } else if self.grid.is_wall(start) {
+            Some(NoPathReason::StartBlocked)
+        } else if self.ends.iter().all(|&end| self.grid.is_wall(end)) {
+            Some(NoPathReason::EndBlocked)
+        } else if self
+            .waypoints
+            .iter()
+            .any(|&waypoint| self.grid.is_wall(waypoint))
+        {
+            Some(NoPathReason::WaypointBlocked)
+        } else {
+            None
+        }
+    }
+
+    /// Searches the `path_count` cheapest routes. Routes through waypoints are not split.
+    fn update_alternatives(&mut self) {
+        self.alternatives = match self.segment_stops(0) {
+            Some((from, to)) if self.path_count > 1 && self.waypoints.is_empty() => {
+                pathfind::k_shortest_paths(&self.grid, from, &to, &self.options, self.path_count)
+            }
+            _ => Vec::new(),
+        };
+    }
+
+    fn set_path_count(&mut self, path_count: usize) {
+        let path_count = path_count.clamp(1, MAX_PATH_COUNT);
+        if self.path_count != path_count {
+            self.path_count = path_count;
+            self.update_alternatives();
+        }
+    }
+
+    fn update_reachable(&mut self) {
+        self.reachable = match self.start {
+            Some(start) if self.show_reachable => {
+                pathfind::reachable(&self.grid, start, &self.options)
+            }
+            _ => Vec::new(),
+        };
+    }
+
+    /// Starts searching segment `segment` of the route.
+    fn start_segment(&mut self) {
+        let (from, to) = self.segment_stops(self.segment).unwrap();
+        match &mut self.search {
+            Some(search) => search.restart(&self.grid, from, &to, &self.options),
+            None => self.search = Some(Search::new(&self.grid, from, &to, &self.options)),
+        }
+        if self.search.as_ref().is_some_and(Search::is_finished) {
+            self.finish_segment();
+        }
+    }
+
+    /// Appends the finished segment to the path and moves on to the next one.
+    fn finish_segment(&mut self) {
+        let Some(search) = &self.search else {
+            return;
+        };
+
+        let result = search.result();
+        self.segment_numcalc += result.numcalc;
+        self.stat_open_max = self.stat_open_max.max(search.open_max());
+        let Some(path) = result.path.clone() else {
+            self.fail_route();
+            return;
+        };
+        self.path.extend(path);
+        self.path_cost += result.cost;
+        let goal = result.goal;
+
+        if self.segment < self.waypoints.len() {
+            self.segment += 1;
+            self.start_segment();
+            return;
+        }
+
+        self.finish_route(goal);
+    }
+
+    /// Settles a route found through every segment, ending at `goal`.
+    fn finish_route(&mut self, goal: Option<Pos>) {
+        self.path_status = PathStatus::Found;
+        self.goal = goal;
+        if let Some(start) = self.start
+            && has_lines_of_sight(self.options.topology)
+        {
+            self.smoothed_path =
+                pathfind::smooth_path(&self.grid, start, &self.path, &self.waypoints);
+        }
+        if self.options.algorithm == Algorithm::GreedyBestFirst
+            && !self.replanning
+            && self.stat_optimal_cost.is_none()
+        {
+            self.stat_optimal_cost = Some(self.route(Algorithm::AStar).cost);
+        }
+    }
+
+    /// Updates the D* Lite route of each segment, starting over on segments whose ends or
+    /// options changed.
+    fn replan(&mut self) {
+        let started = Instant::now();
+        let stops: Vec<(Pos, Vec<Pos>)> = (0..=self.waypoints.len())
+            .filter_map(|segment| self.segment_stops(segment))
+            .collect();
+        self.replanners.truncate(stops.len());
+        let mut goal = None;
+        let mut found = true;
+        for (segment, (from, to)) in stops.iter().enumerate() {
+            match self.replanners.get_mut(segment) {
+                Some(replanner) if replanner.can_replan(&self.grid, to, &self.options) => {
+                    replanner.replan(&self.grid, *from);
+                }
+                Some(replanner) => {
+                    *replanner = DStarLite::new(&self.grid, *from, to, &self.options);
+                }
+                None => self
+                    .replanners
+                    .push(DStarLite::new(&self.grid, *from, to, &self.options)),
+            }
+
+            let result = self.replanners[segment].result();
+            self.stat_numcalc += result.numcalc;
+            match &result.path {
+                Some(path) if found => {
+                    self.path.extend(path);
+                    self.path_cost += result.cost;
+                    goal = result.goal;
+                }
+                _ => found = false,
+            }
+        }
+        self.stat_time_ms = started.elapsed().as_secs_f64() * 1000.0;
+        if stops.is_empty() {
+            return;
+        }
+
+        self.stat_full_numcalc = Some(self.route(Algorithm::AStar).numcalc);
+        if found {
+            self.finish_route(goal);
+        } else {
+            self.fail_route();
+        }
+    }
+
+    /// Drops the path once a stop turns out unreachable, which fails the whole route. Routes
+    /// that only the wall clearance blocks are told apart.
+    fn fail_route(&mut self) {
+        self.path = Vec::new();
+        self.path_cost = 0;
+        let unconstrained = Options {
+            clearance_radius: 0,
+            ..self.options
+        };
+        self.path_status = if self.options.clearance_radius > 0
+            && self.route_with(&unconstrained).path.is_some()
+        {
+            PathStatus::NoClearPath
+        } else {
+            PathStatus::NoPath(NoPathReason::Disconnected)
+        };
+    }
+
+    /// Searches every segment of the route with `algorithm` in one go.
+    fn route(&self, algorithm: Algorithm) -> Route {
+        self.route_with(&Options {
+            algorithm,
+            ..self.options
+        })
+    }
+
+    fn route_with(&self, options: &Options) -> Route {
+        let mut route = Route {
+            path: Some(Vec::new()),
+            cost: 0,
+            numcalc: 0,
+        };
+        for (from, to) in
+            (0..=self.waypoints.len()).filter_map(|segment| self.segment_stops(segment))
+        {
+            let mut search = Search::new(&self.grid, from, &to, options);
+            search.run(&self.grid);
+            let result = search.result();
+            route.numcalc += result.numcalc;
+            route.cost += result.cost;
+            match (&mut route.path, &result.path) {
+                (Some(path), Some(segment)) => path.extend(segment),
+                _ => route.path = None,
+            }
+        }
+        route
+    }
+
+    fn set_compare_algorithm(&mut self, compare_algorithm: Option<Algorithm>) {
+        self.compare_algorithm = compare_algorithm;
+        self.comparison = compare_algorithm
+            .filter(|_| self.segment_stops(0).is_some())
+            .map(|algorithm| self.route(algorithm));
+    }
+
+    /// Runs up to `steps` expansions of the current search, moving through the segments.
+    fn advance(&mut self, steps: u32) {
+        let started = Instant::now();
+        let mut remaining = steps;
+        while remaining > 0
+            && let Some(search) = &mut self.search
+            && !search.is_finished()
+        {
+            remaining -= 1;
+            self.steps_taken += 1;
+            let finished = search.step(&self.grid);
+            if self.animate {
+                self.open_history.push(search.open_len());
+            }
+            if finished {
+                self.finish_segment();
+            }
+        }
+        self.stat_time_ms += started.elapsed().as_secs_f64() * 1000.0;
+        self.publish_result();
+        if self.animate && self.steps_taken > 0 && self.path_status != PathStatus::Searching {
+            self.replay_steps.get_or_insert(self.steps_taken);
+        }
+    }
+
+    /// Takes back the latest expansion of the animated search and pauses it. Searches are
+    /// deterministic, so replaying all but the last step rebuilds the earlier state exactly
+    /// without keeping a history of states.
+    fn step_back(&mut self) {
+        if !self.animate {
+            return;
+        }
+        if let Some(steps) = self.steps_taken.checked_sub(1) {
+            self.scrub_to(steps);
+        }
+    }
+
+    /// Pauses the animated search after its first `steps` expansions, keeping the span of the
+    /// replay bar so the rest can be scrubbed back in.
+    fn scrub_to(&mut self, steps: u32) {
+        let replay_steps = self.replay_steps;
+        self.paused = true;
+        self.restart_search();
+        self.advance(steps);
+        self.replay_steps = replay_steps;
+    }
+
+    fn publish_result(&mut self) {
+        let Some(search) = &self.search else {
+            return;
+        };
+
+        self.stat_numcalc = self.segment_numcalc;
+        self.stat_open_max = self.stat_open_max.max(search.open_max());
+        if !search.is_finished() {
+            self.stat_numcalc += search.result().numcalc;
+            self.path_status = PathStatus::Searching;
+        }
+    }
+}
+
+/// Zoom after scrolling the wheel by `wheel_y`. Platforms report anything from fractions to
+/// hundreds per notch, so one event never zooms by more than a single step.
+fn scroll_zoom(zoom: f32, wheel_y: f32) -> f32 {
+    (zoom * ZOOM_STEP.powf(wheel_y.clamp(-1.0, 1.0))).clamp(MIN_ZOOM, MAX_ZOOM)
+}
+
+fn format_cell(pos: Option<Pos>, none: &str) -> String {
+    match pos {
+        Some(Pos(r, c)) => format!("({r}, {c})"),
+        None => none.to_owned(),
+    }
+}
+
+fn terrain_color(cost: u64) -> Color {
+    match cost {
+        2 => GRASS_COLOR,
+        5 => SWAMP_COLOR,
+        _ => {
+            let t = (cost.min(MAX_TERRAIN_COST) - 1) as f32 / (MAX_TERRAIN_COST - 1) as f32;
+            Color::new(0.55, 0.35, 0.1, 0.25 + 0.65 * t)
+        }
+    }
+}
+
+/// Color of connected region `component`. Golden-ratio steps keep neighbouring labels apart.
+fn component_color(component: usize) -> Color {
+    let hue = (component as f32 * 0.618_034).fract();
+    let Color { r, g, b, .. } = macroquad::color::hsl_to_rgb(hue, 0.7, 0.5);
+    Color::new(r, g, b, 0.5)
+}
+
+// height of a row of hexagons per unit of width, sqrt(3) / 2
+const HEX_ROW_HEIGHT: f32 = 0.866_025_4;
+// distance from the center of a hexagon one unit wide to its corners, 1 / sqrt(3)
+const HEX_RADIUS: f32 = 0.577_350_3;
+
+/// World position of the center of `pos`. Square cells are one unit wide; hex rows are
+/// shifted half a cell right of the row above.
+fn cell_center(topology: Topology, pos: Pos) -> Vec2 {
+    match topology {
+        Topology::Hex => vec2(
+            pos.1 as f32 + 0.5 * pos.0 as f32 + 0.5,
+            pos.0 as f32 * HEX_ROW_HEIGHT + 0.5,
+        ),
+        Topology::Square4 | Topology::Square8 => vec2(pos.1 as f32 + 0.5, pos.0 as f32 + 0.5),
+    }
+}
+
+/// Cell that the world point `point` lies in, which may be outside the grid.
+fn cell_at(topology: Topology, point: Vec2) -> Pos {
+    match topology {
+        Topology::Hex => {
+            let r = (point.y - 0.5) / HEX_ROW_HEIGHT;
+            let c = point.x - 0.5 - 0.5 * r;
+            // round in cube coordinates, fixing up the axis that rounded furthest
+            let s = -r - c;
+            let (mut rr, mut rc, rs) = (r.round(), c.round(), s.round());
+            let (dr, dc, ds) = ((rr - r).abs(), (rc - c).abs(), (rs - s).abs());
+            if dr > dc && dr > ds {
+                rr = -rc - rs;
+            } else if dc > ds {
+                rc = -rr - rs;
+            }
+            Pos(rr as i64, rc as i64)
+        }
+        Topology::Square4 | Topology::Square8 => {
+            Pos(point.y.floor() as i64, point.x.floor() as i64)
+        }
+    }
+}
+
+/// Width and height of the world area covered by a grid, starting at the origin.
+fn grid_extent(topology: Topology, rows: u64, cols: u64) -> Vec2 {
+    match topology {
+        Topology::Hex => vec2(
+            cols as f32 + 0.5 * (rows as f32 - 1.0),
+            (rows as f32 - 1.0) * HEX_ROW_HEIGHT + 1.0,
+        ),
+        Topology::Square4 | Topology::Square8 => vec2(cols as f32, rows as f32),
+    }
+}
+
+fn draw_cell(topology: Topology, pos: Pos, color: Color) {
+    match topology {
+        Topology::Hex => {
+            let center = cell_center(topology, pos);
+            draw_poly(center.x, center.y, 6, HEX_RADIUS, 30.0, color);
+        }
+        Topology::Square4 | Topology::Square8 => {
+            draw_rectangle(pos.1 as f32, pos.0 as f32, 1.0, 1.0, color)
+        }
+    }
+}
+
+fn draw_cell_lines(topology: Topology, pos: Pos, thickness: f32, color: Color) {
+    match topology {
+        Topology::Hex => {
+            let center = cell_center(topology, pos);
+            draw_poly_lines(center.x, center.y, 6, HEX_RADIUS, 30.0, thickness, color);
+        }
+        Topology::Square4 | Topology::Square8 => {
+            draw_rectangle_lines(pos.1 as f32, pos.0 as f32, 1.0, 1.0, thickness, color)
+        }
+    }
+}
+
+/// Lines between all cells of a `rows` by `cols` grid. Square grids are drawn one line per
+/// row and column edge rather than cell by cell.
+fn draw_grid_lines(topology: Topology, rows: u64, cols: u64, thickness: f32, color: Color) {
+    match topology {
+        Topology::Hex => {
+            for r in 0..rows as i64 {
+                for c in 0..cols as i64 {
+                    draw_cell_lines(topology, Pos(r, c), thickness, color);
+                }
+            }
+        }
+        Topology::Square4 | Topology::Square8 => {
+            let (width, height) = (cols as f32, rows as f32);
+            for r in 0..=rows {
+                draw_line(0.0, r as f32, width, r as f32, thickness, color);
+            }
+            for c in 0..=cols {
+                draw_line(c as f32, 0.0, c as f32, height, thickness, color);
+            }
+        }
+    }
+}
+
+/// Outlines the block of cells between two opposite corners.
+fn draw_area_lines(topology: Topology, a: Pos, b: Pos, thickness: f32, color: Color) {
+    let (top, bottom) = (a.0.min(b.0), a.0.max(b.0));
+    let (left, right) = (a.1.min(b.1), a.1.max(b.1));
+    match topology {
+        // a block of hexagons is a parallelogram with a ragged edge, so each cell is outlined
+        Topology::Hex => {
+            for r in top..=bottom {
+                for c in left..=right {
+                    draw_cell_lines(topology, Pos(r, c), thickness, color);
+                }
+            }
+        }
+        Topology::Square4 | Topology::Square8 => draw_rectangle_lines(
+            left as f32,
+            top as f32,
+            (right - left + 1) as f32,
+            (bottom - top + 1) as f32,
+            thickness,
+            color,
+        ),
+    }
+}
+
+/// Draws the whole grid scaled down into the screen at `origin`, `scale` pixels to a cell, and
+/// outlines the part the camera shows. Nothing is drawn while all of the grid is in view.
+fn draw_minimap(context: &Context, origin: Vec2, scale: f32) {
+    let extent = context.grid_extent();
+    let half_view = context.half_view();
+    let view_min = context.camera.target - half_view;
+    let view_max = context.camera.target + half_view;
+    if view_min.cmple(Vec2::ZERO).all() && view_max.cmpge(extent).all() {
+        return;
+    }
+
+    let topology = context.options.topology;
+    let to_screen = |point: Vec2| origin + point * scale;
+    let size = extent * scale;
+    draw_rectangle(origin.x, origin.y, size.x, size.y, MINIMAP_BACKGROUND);
+    // the cells of a row sit side by side on hexes too, if shifted
+    for (r, first, len) in wall_runs(&context.grid) {
+        let corner = to_screen(cell_center(topology, Pos(r, first)) - Vec2::splat(0.5));
+        draw_rectangle(corner.x, corner.y, scale * len as f32, scale, LIGHTGRAY);
+    }
+
+    if let Some(start) = context.start {
+        let steps = std::iter::once(start)
+            .chain(context.path.iter().copied())
+            .zip(context.path.iter().copied());
+        for (a, b) in steps {
+            if !is_jump(&context.grid, context.options.wrap, a, b) {
+                let (a, b) = (
+                    to_screen(cell_center(topology, a)),
+                    to_screen(cell_center(topology, b)),
+                );
+                draw_line(a.x, a.y, b.x, b.y, 1.5, GREEN);
+            }
+        }
+        let center = to_screen(cell_center(topology, start));
+        draw_circle(center.x, center.y, 3.0, WHITE);
+    }
+    for &end in &context.ends {
+        let center = to_screen(cell_center(topology, end));
+        draw_circle(center.x, center.y, 3.0, GOLD);
+    }
+
+    let view_min = to_screen(view_min.max(Vec2::ZERO));
+    let view_max = to_screen(view_max.min(extent));
+    let view_size = view_max - view_min;
+    draw_rectangle_lines(
+        view_min.x,
+        view_min.y,
+        view_size.x,
+        view_size.y,
+        1.5,
+        YELLOW,
+    );
+}
+
+/// Each unbroken horizontal run of walls as its row, first column and length.
+fn wall_runs(grid: &Grid) -> impl Iterator<Item = (i64, i64, i64)> + '_ {
+    let cols = grid.cols() as i64;
+    (0..grid.rows() as i64).flat_map(move |r| {
+        let mut c = 0;
+        std::iter::from_fn(move || {
+            while c < cols && !grid.is_wall(Pos(r, c)) {
+                c += 1;
+            }
+            let first = c;
+            while c < cols && grid.is_wall(Pos(r, c)) {
+                c += 1;
+            }
+            (c > first).then_some((r, first, c - first))
+        })
+    })
+}
+
+/// Fills the walls, one rectangle per run of them on square cells.
+fn draw_walls(grid: &Grid, topology: Topology) {
+    for (r, first, len) in wall_runs(grid) {
+        match topology {
+            Topology::Hex => {
+                for c in first..first + len {
+                    draw_cell(topology, Pos(r, c), WALL_COLOR);
+                }
+            }
+            Topology::Square4 | Topology::Square8 => {
+                draw_rectangle(first as f32, r as f32, len as f32, 1.0, WALL_COLOR)
+            }
+        }
+    }
+}
+
+/// Whether lines of sight, as smoothing and Theta* trace them, exist on `topology`. They are
+/// traced across square cells.
+fn has_lines_of_sight(topology: Topology) -> bool {
+    topology != Topology::Hex
+}
+
+/// Whether the step from `from` to `to` jumps through a portal or across a wrapped edge.
+fn is_jump(grid: &Grid, wrap: bool, from: Pos, to: Pos) -> bool {
+    grid.portal_partner(from) == Some(to) || (wrap && grid.nearest_image(from, to) != to)
+}
+
+/// Length of `path` from `start` in cells, counting jumps as instant.
+fn path_length(grid: &Grid, options: &Options, start: Pos, path: &[Pos]) -> f32 {
+    let center = |pos: Pos| cell_center(options.topology, pos);
+    std::iter::once(start)
+        .chain(path.iter().copied())
+        .zip(path.iter().copied())
+        .filter(|&(from, to)| !is_jump(grid, options.wrap, from, to))
+        .map(|(from, to)| center(from).distance(center(to)))
+        .sum()
+}
+
+/// Center of the point `distance` cells along `path` from `start`.
+fn point_along_path(
+    grid: &Grid,
+    options: &Options,
+    start: Pos,
+    path: &[Pos],
+    distance: f32,
+) -> Vec2 {
+    let wrap = options.wrap;
+    let center = |pos: Pos| cell_center(options.topology, pos);
+    let mut remaining = distance;
+    let mut prev = start;
+    for &pos in path {
+        if !is_jump(grid, wrap, prev, pos) {
+            let step = center(prev).distance(center(pos));
+            if remaining < step {
+                return center(prev).lerp(center(pos), remaining / step);
+            }
+            remaining -= step;
+        }
+        prev = pos;
+    }
+    center(prev)
+}
+
+/// Screen area of the replay bar, along the bottom edge left of the minimap.
+fn scrub_bar() -> Rect {
+    Rect::new(
+        10.0,
+        screen_height() - 24.0,
+        screen_width() - MINIMAP_SIZE - 30.0,
+        12.0,
+    )
+}
+
+/// The replay bar, filled up to the expansion the search is paused at.
+fn draw_scrub_bar(steps: u32, replay_steps: u32) {
+    let bar = scrub_bar();
+    let t = steps.min(replay_steps) as f32 / replay_steps as f32;
+    draw_rectangle(bar.x, bar.y, bar.w, bar.h, Color::new(1.0, 1.0, 1.0, 0.15));
+    draw_rectangle(bar.x, bar.y, bar.w * t, bar.h, SKYBLUE);
+    draw_rectangle(
+        bar.x + bar.w * t - 2.0,
+        bar.y - 3.0,
+        4.0,
+        bar.h + 6.0,
+        WHITE,
+    );
+    draw_text(
+        &format!("step {steps}/{replay_steps}"),
+        bar.x + 4.0,
+        bar.y + bar.h - 1.0,
+        16.0,
+        WHITE,
+    );
+}
+
+/// Draws `values` as a line chart in the screen rectangle at `x`, `y`, taking the largest value
+/// of each pixel column when there are more values than pixels.
+fn draw_sparkline(values: &[usize], x: f32, y: f32, width: f32, height: f32, color: Color) {
+    draw_rectangle_lines(x, y, width, height, 1.0, GRAY);
+    let Some(&max) = values.iter().max().filter(|&&max| max > 0) else {
+        return;
+    };
+    let columns = values.len().min(width as usize).max(2);
+    let point = |column: usize| {
+        let from = column * values.len() / columns;
+        let to = ((column + 1) * values.len() / columns).max(from + 1);
+        let value = values[from..to].iter().max().unwrap();
+        vec2(
+            x + width * column as f32 / (columns - 1) as f32,
+            y + height * (1.0 - *value as f32 / max as f32),
+        )
+    };
+    for column in 1..columns {
+        let (a, b) = (point(column - 1), point(column));
+        draw_line(a.x, a.y, b.x, b.y, 1.5, color);
+    }
+}
+
+/// The key help on a dark box with the top left corner at `x`, `y`.
+fn draw_help(x: f32, y: f32) {
+    let width = KEY_HELP
+        .iter()
+        .map(|line| measure_text(line, None, 20, 1.0).width)
+        .fold(0.0, f32::max)
+        + 20.0;
+    let height = KEY_HELP.len() as f32 * 20.0 + 10.0;
+    draw_rectangle(x, y, width, height, Color::new(0.0, 0.0, 0.0, 0.8));
+    for (i, line) in KEY_HELP.iter().enumerate() {
+        draw_text(line, x + 10.0, y + 20.0 + 20.0 * i as f32, 20.0, WHITE);
+    }
+}
+
+/// Names and colors of what is drawn on the grid right now, for the legend.
+fn legend_entries(context: &Context, heatmap: bool) -> Vec<(&'static str, Color)> {
+    let grid = &context.grid;
+    let costs: Vec<u64> = (0..grid.rows() as i64)
+        .flat_map(|r| (0..grid.cols() as i64).map(move |c| Pos(r, c)))
+        .filter_map(|pos| grid.cost(pos))
+        .filter(|&cost| cost > 1)
+        .collect();
+
+    let mut entries = vec![("wall", WALL_COLOR)];
+    for &(name, cost) in &BRUSHES {
+        if let Some(cost) = cost
+            && cost > 1
+            && costs.contains(&cost)
+        {
+            entries.push((name, terrain_color(cost)));
+        }
+    }
+    if costs.iter().any(|&cost| cost != 2 && cost != 5) {
+        entries.push(("other terrain", terrain_color(MAX_TERRAIN_COST)));
+    }
+    if grid.has_one_way_cells() {
+        entries.push(("one-way exit", ARROW_COLOR));
+    }
+    if !grid.portals().is_empty() {
+        entries.push(("portal", PORTAL_COLOR));
+    }
+    if context.show_wall_distances {
+        entries.push(("next to a wall", Color::new(1.0, 0.3, 0.0, 0.7)));
+        entries.push(("far from walls", Color::new(0.0, 0.3, 1.0, 0.1)));
+    }
+    if (context.show_search || context.animate) && context.search.is_some() {
+        entries.push(("closed set", CLOSED_COLOR));
+        entries.push(("open set", OPEN_COLOR));
+    }
+    if heatmap {
+        entries.push(("near the start", Color::new(0.0, 0.2, 1.0, 0.7)));
+        entries.push(("far from the start", Color::new(1.0, 0.2, 0.0, 0.7)));
+    }
+    if context.show_components {
+        entries.push(("connected region", component_color(0)));
+    }
+    if !context.reachable.is_empty() {
+        entries.push(("reachable", REACHABLE_COLOR));
+        entries.push(("unreachable", UNREACHABLE_COLOR));
+    }
+    if context.path_status == PathStatus::Found {
+        entries.push(("path", GREEN));
+        if context.goal.is_some() {
+            entries.push(("reached end", GOAL_RING_COLOR));
+        }
+    }
+    if context.alternatives.len() > 1 {
+        entries.push(("alternative route", ALTERNATIVE_COLOR));
+    }
+    if let Some(comparison) = &context.comparison
+        && comparison.path.is_some()
+    {
+        entries.push(("compared algorithm", COMPARE_COLOR));
+    }
+    if context.show_smoothed && !context.smoothed_path.is_empty() {
+        entries.push(("smoothed path", SKYBLUE));
+    }
+    if context.show_walker && context.path_status == PathStatus::Found {
+        entries.push(("walker", WALKER_COLOR));
+    }
+    if context.show_sight && has_lines_of_sight(context.options.topology) {
+        entries.push(("clear line of sight", GREEN));
+        entries.push(("blocked line of sight", RED));
+    }
+    entries
+}
+
+/// A box of color swatches, each followed by its name, with the top right corner at `right`, `y`.
+fn draw_legend(entries: &[(&str, Color)], right: f32, y: f32) {
+    let (row_height, swatch) = (20.0, 14.0);
+    let width = entries
+        .iter()
+        .map(|(name, _)| measure_text(name, None, 20, 1.0).width)
+        .fold(0.0, f32::max)
+        + swatch
+        + 20.0;
+    let height = entries.len() as f32 * row_height + 6.0;
+    let x = right - width;
+    draw_rectangle(x, y, width, height, Color::new(0.0, 0.0, 0.0, 0.7));
+    draw_rectangle_lines(x, y, width, height, 1.0, GRAY);
+    for (i, (name, color)) in entries.iter().enumerate() {
+        let top = y + 6.0 + i as f32 * row_height;
+        draw_rectangle(x + 6.0, top, swatch, swatch, *color);
+        draw_rectangle_lines(x + 6.0, top, swatch, swatch, 1.0, GRAY);
+        draw_text(name, x + swatch + 12.0, top + swatch - 2.0, 20.0, WHITE);
+    }
+}
+
+/// Fills the cells of `path` after `start`. Corners that are not adjacent, as in any-angle
+/// paths, are joined through the cells on the line of sight between them.
+fn draw_path_cells(grid: &Grid, options: &Options, start: Pos, path: &[Pos], color: Color) {
+    let mut prev_point = start;
+    for &p in path {
+        if is_jump(grid, options.wrap, prev_point, p) {
+            draw_cell(options.topology, p, color);
+        } else {
+            for pos in pathfind::bresenham(prev_point, p).into_iter().skip(1) {
+                draw_cell(options.topology, pos, color);
+            }
+        }
+        prev_point = p;
+    }
+}
+
+/// Draws the steps of `path` from `start`, leaving out portal teleports and splitting steps
+/// that wrap around an edge.
+fn draw_path(
+    grid: &Grid,
+    options: &Options,
+    start: Pos,
+    path: &[Pos],
+    thickness: f32,
+    color: Color,
+) {
+    let center = |pos: Pos| cell_center(options.topology, pos);
+    let mut prev_point = start;
+    for p in path.iter() {
+        let p1 = center(prev_point);
+        let p2 = center(*p);
+        let image = if options.wrap {
+            grid.nearest_image(prev_point, *p)
+        } else {
+            *p
+        };
+        if grid.portal_partner(prev_point) == Some(*p) {
+            // teleports are shown by the portal link itself
+        } else if image == *p {
+            draw_line(p1.x, p1.y, p2.x, p2.y, thickness, color);
+        } else {
+            // a step across the edge leaves on one side and enters on the other
+            let half_step = (center(image) - p1) / 2.0;
+            draw_line(
+                p1.x,
+                p1.y,
+                p1.x + half_step.x,
+                p1.y + half_step.y,
+                thickness,
+                color,
+            );
+            draw_line(
+                p2.x - half_step.x,
+                p2.y - half_step.y,
+                p2.x,
+                p2.y,
+                thickness,
+                color,
+            );
+        }
+        prev_point = *p;
+    }
+}
+
+pub(crate) fn draw_text_centered(
+    text: &str,
+    x: f32,
+    y: f32,
+    font_size: f32,
+    font_scale: f32,
+    color: Color,
+) {
+    let center = get_text_center(text, None, font_size as u16, font_scale, 0.0);
+    draw_text_ex(
+        text,
+        x - center.x,
+        y - center.y,
+        TextParams {
+            font_size: font_size as u16,
+            font_scale,
+            color,
+            ..Default::default()
+        },
+    );
+}
+
+fn main() {
+    let args = Args::parse();
+    if args.stdin {
+        if let Err(err) = bench::solve_stdin(&Options::default(), args.ascii) {
+            eprintln!("{err}");
+            std::process::exit(1);
+        }
+        return;
+    }
+    if let Some(iterations) = args.bench {
+        if !args.load {
+            eprintln!("--bench needs a map given with --load");
+            std::process::exit(1);
+        }
+        if let Err(err) = bench::run(&args.map_path, iterations, &Options::default()) {
+            eprintln!("{err}");
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    macroquad::Window::from_config(conf(), run(args));
+}
+
+async fn run(args: Args) {
+    let rng = Rng::new(args.seed.unwrap_or_else(|| {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_nanos() as u64)
+    }));
+
+    // settings edited by hand may be out of range
+    let settings = settings::load();
+    let zoom = settings.zoom.clamp(MIN_ZOOM, MAX_ZOOM);
+    let cell_size = vec2(settings.cell_w, settings.cell_h)
+        .clamp(Vec2::splat(MIN_CELL_SCALE), Vec2::splat(MAX_CELL_SCALE));
+    let rows = settings.rows.clamp(MIN_GRID_SIZE, MAX_GRID_SIZE);
+    let cols = settings.cols.clamp(MIN_GRID_SIZE, MAX_GRID_SIZE);
+    let mut options = settings.options;
+    options.heuristic_weight = options.heuristic_weight.clamp(1.0, MAX_HEURISTIC_WEIGHT);
+    options.clearance_weight = options.clearance_weight.min(MAX_CLEARANCE_WEIGHT);
+    options.clearance_radius = options.clearance_radius.min(MAX_CLEARANCE_RADIUS);
+    if options.orthogonal_cost == 0
+        || options.orthogonal_cost > MAX_MOVE_COST
+        || options.diagonal_cost < options.orthogonal_cost
+    {
+        options.orthogonal_cost = Options::default().orthogonal_cost;
+        options.diagonal_cost = Options::default().diagonal_cost;
+    }
+    // a diagonal dearer than two orthogonal steps is never taken anyway
+    options.diagonal_cost = options.diagonal_cost.min(2 * options.orthogonal_cost);
+
+    clear_background(BLACK);
+    prevent_quit();
+
+    let mut context = Context {
+        mouse_grid: None,
+        control_state: ControlState::Grid,
+        zoom,
+        cell_size,
+        camera: Camera2D {
+            zoom: vec2(zoom * screen_height() / screen_width(), zoom) * cell_size,
+            target: vec2(cols as f32 / 2.0, rows as f32 / 2.0),
+            offset: vec2(0.0, 0.0),
+            ..Default::default()
+        },
+        window_size: vec2(screen_width(), screen_height()),
+        view_moved: false,
+        grid: Grid::new(rows, cols),
+        brush: 0,
+        brush_size: 1,
+        fill_density: 30,
+        cave_iterations: 4,
+        symmetry: Symmetry::Off,
+        start: None,
+        ends: Vec::new(),
+        goal: None,
+        path: Vec::new(),
+        path_cost: 0,
+        path_status: PathStatus::Unset,
+        smoothed_path: Vec::new(),
+
+        options,
+        search: None,
+        replanning: false,
+        replanners: Vec::new(),
+        portal_anchor: None,
+        waypoints: Vec::new(),
+        history: History::default(),
+        segment: 0,
+        segment_numcalc: 0,
+        dirty: false,
+        frames_since_paint: 0,
+        show_search: false,
+        show_smoothed: false,
+        path_style: PathStyle::Line,
+        gridlines: Gridlines::Auto,
+        show_sight: false,
+        nudge_end: false,
+        hold_to_place: settings.hold_to_place,
+        snap_to_open: settings.snap_to_open,
+        show_reachable: false,
+        reachable: Vec::new(),
+        show_components: false,
+        show_order: false,
+        components: None,
+        show_wall_distances: false,
+        show_legend: false,
+        show_help: false,
+        wall_distances: None,
+        animate: settings.animate,
+        animate_setting: None,
+        paused: false,
+        responsive: settings.responsive,
+        speed: settings.speed.clamp(1, MAX_ANIMATION_SPEED),
+        steps_taken: 0,
+        replay_steps: None,
+        show_walker: false,
+        walk_loop: true,
+        walk_distance: 0.0,
+        walk_speed: DEFAULT_WALK_SPEED,
+
+        maze_generator: maze::Generator::RecursiveBacktracker,
+        map_seed: rng.seed(),
+        rng,
+
+        map_path: args.map_path,
+        overwrite_pending: false,
+        message: None,
+        record_dir: None,
+        recorded_frames: 0,
+
+        stat_numcalc: 0,
+        stat_open_max: 0,
+        open_history: Vec::new(),
+        frame_times: VecDeque::with_capacity(FRAME_TIME_WINDOW),
+        stat_time_ms: 0.0,
+        stat_optimal_cost: None,
+        stat_full_numcalc: None,
+        compare_algorithm: None,
+        comparison: None,
+        path_count: 1,
+        alternatives: Vec::new(),
+    };
+
+    // only the animated search has steps worth recording, so animation is on before the
+    // loaded map is first searched
+    if let Some(dir) = args.record {
+        match std::fs::create_dir_all(&dir) {
+            Ok(()) => {
+                context.animate_setting = Some(context.animate);
+                context.animate = true;
+                context.record_dir = Some(dir);
+            }
+            Err(err) => eprintln!("cannot record to {}: {err}", dir.display()),
+        }
+    }
+    if args.load {
+        context.load_map();
+    }
+    if let Some(path) = &args.image {
+        context.load_image(path);
+    }
+
+    loop {
+        if is_key_pressed(KeyCode::Escape) || is_quit_requested() {
+            context.save_settings();
+            return;
+        }
+
+        if context.frame_times.len() == FRAME_TIME_WINDOW {
+            context.frame_times.pop_front();
+        }
+        context.frame_times.push_back(get_frame_time());
+
+        let window_size = vec2(screen_width(), screen_height());
+        if window_size != context.window_size {
+            context.window_size = window_size;
+            if !context.view_moved {
+                context.fit_camera();
+            }
+        }
+
+        let mouse_wheel_y = mouse_wheel().1;
+        let mouse_before_zoom = context.camera.screen_to_world(mouse_position().into());
+        context.zoom = scroll_zoom(context.zoom, mouse_wheel_y);
+        context.camera.zoom = context.camera_zoom();
+        if mouse_wheel_y != 0.0 {
+            context.view_moved = true;
+            // keep the world point under the cursor fixed
+            let mouse_after_zoom = context.camera.screen_to_world(mouse_position().into());
+            context.camera.target += mouse_before_zoom - mouse_after_zoom;
+        }
+
+        if is_key_down(KeyCode::LeftShift) || is_key_down(KeyCode::RightShift) {
+            let mut direction = Vec2::ZERO;
+            for (key, step) in [
+                (KeyCode::Up, vec2(0.0, -1.0)),
+                (KeyCode::Down, vec2(0.0, 1.0)),
+                (KeyCode::Left, vec2(-1.0, 0.0)),
+                (KeyCode::Right, vec2(1.0, 0.0)),
+            ] {
+                if is_key_down(key) {
+                    direction += step;
+                }
+            }
+            if direction != Vec2::ZERO {
+                // the window is 2 / zoom world units tall
+                let step = PAN_SPEED * 2.0 / context.zoom * get_frame_time();
+                context.camera.target += direction * step / context.cell_size;
+                context.view_moved = true;
+            }
+        }
+
+        let mouse_pos_world = context.camera.screen_to_world(mouse_position().into());
+        let mouse_cell = cell_at(context.options.topology, mouse_pos_world);
+        context.mouse_grid = if context.grid.contains(mouse_cell) {
+            Some(mouse_cell)
+        } else {
+            None
+        };
+
+        match context.control_state {
+            ControlState::Grid => 'l: {
+                if is_mouse_button_pressed(MouseButton::Middle) {
+                    context.set_control_state(ControlState::Panning);
+                    break 'l;
+                }
+                if context.replay_steps.is_some()
+                    && is_mouse_button_pressed(MouseButton::Left)
+                    && scrub_bar().contains(mouse_position().into())
+                {
+                    context.set_control_state(ControlState::Scrubbing);
+                    break 'l;
+                }
+
+                let shift = is_key_down(KeyCode::LeftShift) || is_key_down(KeyCode::RightShift);
+                let ctrl = is_key_down(KeyCode::LeftControl) || is_key_down(KeyCode::RightControl);
+                if let Some(pos) = context.mouse_grid
+                    && (shift || ctrl)
+                {
+                    let paint = if is_mouse_button_pressed(MouseButton::Left) {
+                        Some(BRUSHES[context.brush].1)
+                    } else if is_mouse_button_pressed(MouseButton::Right) {
+                        Some(Some(1))
+                    } else {
+                        None
+                    };
+                    if let Some(paint) = paint {
+                        context.set_control_state(if ctrl {
+                            ControlState::Line {
+                                anchor: pos,
+                                end: pos,
+                                paint,
+                            }
+                        } else {
+                            ControlState::Rectangle {
+                                anchor: pos,
+                                corner: pos,
+                                paint,
+                            }
+                        });
+                        break 'l;
+                    }
+                }
+
+                if let Some(pos) = context.mouse_grid
+                    && is_mouse_button_pressed(MouseButton::Left)
+                {
+                    // painting over the brush's own terrain clears it back to plain ground
+                    let brush = BRUSHES[context.brush].1;
+                    let paint = if context.grid.cost(pos) == brush {
+                        Some(1)
+                    } else {
+                        brush
+                    };
+                    context.set_control_state(ControlState::Drawing(paint));
+                    break 'l;
+                }
+                if context.mouse_grid.is_some() && is_mouse_button_pressed(MouseButton::Right) {
+                    context.set_control_state(ControlState::Drawing(Some(1)));
+                    break 'l;
+                }
+
+                if ctrl && is_key_pressed(KeyCode::S) {
+                    context.save_map();
+                    break 'l;
+                }
+                if ctrl && is_key_pressed(KeyCode::E) {
+                    context.export_path();
+                    break 'l;
+                }
+                if ctrl && is_key_pressed(KeyCode::G) {
+                    context.export_svg();
+                    break 'l;
+                }
+                if ctrl && is_key_pressed(KeyCode::A) {
+                    context.export_ascii();
+                    break 'l;
+                }
+                if ctrl && is_key_pressed(KeyCode::L) {
+                    context.load_map();
+                    break 'l;
+                }
+                if ctrl && is_key_pressed(KeyCode::Z) {
+                    context.undo();
+                    break 'l;
+                }
+                if ctrl && is_key_pressed(KeyCode::Y) {
+                    context.redo();
+                    break 'l;
+                }
+                if ctrl && is_key_pressed(KeyCode::I) {
+                    context.invert_walls();
+                    break 'l;
+                }
+
+                if shift && is_key_pressed(KeyCode::S) {
+                    context.snap_to_open = !context.snap_to_open;
+                }
+                if context.hold_to_place {
+                    if !ctrl && !shift && is_key_down(KeyCode::S) {
+                        let pos = context.mouse_grid.map(|cursor| context.snap(cursor));
+                        if pos != context.start {
+                            context.report_snap(context.mouse_grid, pos);
+                            context.start = pos;
+                            context.mark_dirty();
+                        }
+                    }
+                    if !ctrl && !shift && is_key_down(KeyCode::E) {
+                        let pos = context.mouse_grid.map(|cursor| context.snap(cursor));
+                        let ends: Vec<Pos> = pos.into_iter().collect();
+                        if context.ends != ends {
+                            context.report_snap(context.mouse_grid, pos);
+                            context.ends = ends;
+                            context.mark_dirty();
+                        }
+                    }
+                } else {
+                    if !ctrl && !shift && is_key_pressed(KeyCode::S) {
+                        context.set_control_state(ControlState::Placing(Endpoint::Start));
+                        break 'l;
+                    }
+                    if !ctrl && !shift && is_key_pressed(KeyCode::E) {
+                        context.set_control_state(ControlState::Placing(Endpoint::End));
+                        break 'l;
+                    }
+                }
+                if shift
+                    && is_key_pressed(KeyCode::E)
+                    && let Some(pos) = context.mouse_grid
+                {
+                    context.toggle_end(pos);
+                }
+
+                if !shift && is_key_pressed(KeyCode::Key1) {
+                    context.set_algorithm(Algorithm::AStar);
+                }
+                if !shift && is_key_pressed(KeyCode::Key2) {
+                    context.set_algorithm(Algorithm::Dijkstra);
+                }
+                if !shift && is_key_pressed(KeyCode::Key3) {
+                    context.set_algorithm(Algorithm::Bfs);
+                }
+                if !shift && is_key_pressed(KeyCode::Key4) {
+                    context.set_algorithm(Algorithm::Bidirectional);
+                }
+                if is_key_pressed(KeyCode::Key5) {
+                    if context.options.topology == Topology::Square8 {
+                        context.set_algorithm(Algorithm::Jps);
+                    } else {
+                        context.message = Some("JPS needs 8-connectivity".to_owned());
+                    }
+                }
+                if is_key_pressed(KeyCode::Key6) {
+                    context.set_algorithm(Algorithm::GreedyBestFirst);
+                }
+                if is_key_pressed(KeyCode::Key7) {
+                    if context.options.topology == Topology::Hex {
+                        context.message = Some("Theta* needs square cells".to_owned());
+                    } else {
+                        context.set_algorithm(Algorithm::ThetaStar);
+                    }
+                }
+                if is_key_pressed(KeyCode::Key8) {
+                    context.start_replanning();
+                }
+                if is_key_pressed(KeyCode::D) {
+                    if shift {
+                        context.set_heuristic(match context.options.heuristic {
+                            Heuristic::Auto => Heuristic::Manhattan,
+                            Heuristic::Manhattan => Heuristic::Euclidean,
+                            Heuristic::Euclidean => Heuristic::Chebyshev,
+                            Heuristic::Chebyshev => Heuristic::Zero,
+                            Heuristic::Zero => Heuristic::Auto,
+                        });
+                    } else {
+                        context.set_topology(match context.options.topology {
+                            Topology::Square4 => Topology::Square8,
+                            Topology::Square8 => Topology::Hex,
+                            Topology::Hex => Topology::Square4,
+                        });
+                    }
+                }
+                if is_key_pressed(KeyCode::K) {
+                    context.set_corner_cutting(!context.options.corner_cutting);
+                }
+                if is_key_pressed(KeyCode::J) {
+                    let sqrt2_costs =
+                        context.options.diagonal_cost < 2 * context.options.orthogonal_cost;
+                    context.set_sqrt2_costs(!sqrt2_costs);
+                }
+                if is_key_pressed(KeyCode::Q) {
+                    context.set_wrap(!context.options.wrap);
+                }
+                for (key, factor) in [
+                    (KeyCode::Key9, 1.0 / CELL_SCALE_STEP),
+                    (KeyCode::Key0, CELL_SCALE_STEP),
+                ] {
+                    if is_key_pressed(key) {
+                        let side = if shift {
+                            &mut context.cell_size.y
+                        } else {
+                            &mut context.cell_size.x
+                        };
+                        *side = (*side * factor).clamp(MIN_CELL_SCALE, MAX_CELL_SCALE);
+                    }
+                }
+                if is_key_pressed(KeyCode::Backslash) {
+                    context.symmetry = context.symmetry.next();
+                }
+                if is_key_pressed(KeyCode::T) {
+                    context.brush = (context.brush + 1) % BRUSHES.len();
+                }
+                if shift && let Some(brush) = BRUSH_KEYS.iter().position(|&key| is_key_pressed(key))
+                {
+                    context.brush = brush;
+                }
+                if is_key_pressed(KeyCode::B) {
+                    let step = if shift { -1 } else { 1 };
+                    context.brush_size = (context.brush_size + step).clamp(1, MAX_BRUSH_SIZE);
+                }
+                if is_key_pressed(KeyCode::U) {
+                    context.show_reachable = !context.show_reachable;
+                    context.update_reachable();
+                }
+                if is_key_pressed(KeyCode::I) {
+                    if shift {
+                        context.show_order = !context.show_order;
+                    } else {
+                        context.show_components = !context.show_components;
+                    }
+                }
+                if is_key_pressed(KeyCode::Y) {
+                    context.show_wall_distances = !context.show_wall_distances;
+                }
+                if is_key_pressed(KeyCode::O) {
+                    if shift {
+                        context.set_open_list(match context.options.open_list {
+                            OpenList::Heap => OpenList::Scan,
+                            OpenList::Scan => OpenList::Heap,
+                        });
+                    } else {
+                        context.show_search = !context.show_search;
+                    }
+                }
+                if is_key_pressed(KeyCode::L) {
+                    if shift {
+                        context.show_sight = !context.show_sight;
+                    } else {
+                        context.show_smoothed = !context.show_smoothed;
+                    }
+                }
+                if is_key_pressed(KeyCode::F1) {
+                    context.show_help = !context.show_help;
+                }
+                if is_key_pressed(KeyCode::Slash) && shift {
+                    context.show_legend = !context.show_legend;
+                } else if is_key_pressed(KeyCode::Slash) {
+                    let next = match context.compare_algorithm {
+                        None => ALGORITHMS.first(),
+                        Some(algorithm) => {
+                            ALGORITHMS.iter().skip_while(|&&a| a != algorithm).nth(1)
+                        }
+                    };
+                    context.set_compare_algorithm(next.copied());
+                }
+                if is_key_pressed(KeyCode::V) {
+                    if shift {
+                        context.set_responsive(!context.responsive);
+                    } else {
+                        context.set_animate(!context.animate);
+                    }
+                }
+                if is_key_pressed(KeyCode::Space) {
+                    context.paused = !context.paused;
+                }
+                if is_key_pressed(KeyCode::N) {
+                    if shift {
+                        context.step_back();
+                    } else {
+                        context.advance(1);
+                    }
+                }
+                if is_key_pressed(KeyCode::Comma) {
+                    if shift {
+                        context.walk_speed = (context.walk_speed / 2.0).max(MIN_WALK_SPEED);
+                    } else {
+                        context.speed = (context.speed / 2).max(1);
+                    }
+                }
+                if is_key_pressed(KeyCode::Period) {
+                    if shift {
+                        context.walk_speed = (context.walk_speed * 2.0).min(MAX_WALK_SPEED);
+                    } else {
+                        context.speed = (context.speed * 2).min(MAX_ANIMATION_SPEED);
+                    }
+                }
+                if is_key_pressed(KeyCode::Z) {
+                    if shift {
+                        context.walk_loop = !context.walk_loop;
+                    } else {
+                        context.show_walker = !context.show_walker;
+                        context.walk_distance = 0.0;
+                    }
+                }
+                if is_key_pressed(KeyCode::M) {
+                    if shift {
+                        context.maze_generator = context.maze_generator.next();
+                    } else {
+                        context.generate_maze();
+                    }
+                }
+                if is_key_pressed(KeyCode::F) {
+                    context.fit_camera();
+                }
+                if is_key_pressed(KeyCode::Tab) {
+                    context.nudge_end = !context.nudge_end;
+                }
+                for (key, direction) in [
+                    (KeyCode::Up, Pos(-1, 0)),
+                    (KeyCode::Down, Pos(1, 0)),
+                    (KeyCode::Left, Pos(0, -1)),
+                    (KeyCode::Right, Pos(0, 1)),
+                ] {
+                    if !shift && is_key_pressed(key) {
+                        context.nudge_endpoint(direction);
+                    }
+                }
+                if is_key_pressed(KeyCode::C) {
+                    if shift {
+                        context.add_wall_frame();
+                    } else {
+                        context.history.begin(&context.grid);
+                        context.grid.clear();
+                        context.history.end(&context.grid);
+                        context.calculate();
+                    }
+                }
+                if is_key_pressed(KeyCode::W)
+                    && let Some(pos) = context.mouse_grid
+                {
+                    context.toggle_waypoint(pos);
+                }
+                if is_key_pressed(KeyCode::P) {
+                    if shift {
+                        context.path_style = match context.path_style {
+                            PathStyle::Line => PathStyle::Cells,
+                            PathStyle::Cells => PathStyle::Line,
+                        };
+                    } else if let Some(pos) = context.mouse_grid {
+                        context.toggle_portal(pos);
+                    }
+                }
+                if is_key_pressed(KeyCode::A)
+                    && let Some(pos) = context.mouse_grid
+                {
+                    context.cycle_exit(pos);
+                }
+                if is_key_pressed(KeyCode::X) {
+                    if shift {
+                        context.place_in_corners();
+                    } else {
+                        context.start = None;
+                        context.ends.clear();
+                        context.waypoints.clear();
+                        context.calculate();
+                    }
+                }
+                if is_key_pressed(KeyCode::R) {
+                    if shift {
+                        context.generate_cave();
+                    } else {
+                        context.random_fill();
+                    }
+                }
+                if is_key_pressed(KeyCode::Enter) {
+                    if shift {
+                        context.set_path_count(context.path_count - 1);
+                    } else {
+                        context.set_path_count(context.path_count + 1);
+                    }
+                }
+                if is_key_pressed(KeyCode::GraveAccent) {
+                    let radius = context.options.clearance_radius;
+                    context.set_clearance_radius(if shift {
+                        radius.saturating_sub(1)
+                    } else {
+                        radius + 1
+                    });
+                }
+                if is_key_pressed(KeyCode::Semicolon) {
+                    if shift {
+                        context.set_clearance_weight(
+                            context.options.clearance_weight.saturating_sub(1),
+                        );
+                    } else {
+                        context.set_heuristic_weight(
+                            context.options.heuristic_weight - HEURISTIC_WEIGHT_STEP,
+                        );
+                    }
+                }
+                if is_key_pressed(KeyCode::Apostrophe) {
+                    if shift {
+                        context.set_clearance_weight(context.options.clearance_weight + 1);
+                    } else {
+                        context.set_heuristic_weight(
+                            context.options.heuristic_weight + HEURISTIC_WEIGHT_STEP,
+                        );
+                    }
+                }
+                if is_key_pressed(KeyCode::G) {
+                    if shift {
+                        context.gridlines = match context.gridlines {
+                            Gridlines::Auto => Gridlines::On,
+                            Gridlines::On => Gridlines::Off,
+                            Gridlines::Off => Gridlines::Auto,
+                        };
+                    } else {
+                        context.set_tie_break(match context.options.tie_break {
+                            TieBreak::Position => TieBreak::HighGscore,
+                            TieBreak::HighGscore => TieBreak::Position,
+                        });
+                    }
+                }
+                if is_key_pressed(KeyCode::LeftBracket) {
+                    if shift {
+                        context.cave_iterations = context.cave_iterations.saturating_sub(1);
+                    } else {
+                        context.fill_density = context.fill_density.saturating_sub(DENSITY_STEP);
+                    }
+                }
+                if is_key_pressed(KeyCode::RightBracket) {
+                    if shift {
+                        context.cave_iterations =
+                            (context.cave_iterations + 1).min(MAX_CAVE_ITERATIONS);
+                    } else {
+                        context.fill_density = (context.fill_density + DENSITY_STEP).min(100);
+                    }
+                }
+                if is_key_pressed(KeyCode::Equal) {
+                    if shift {
+                        context.set_move_cost(context.options.orthogonal_cost + 1);
+                    } else {
+                        context.resize_grid(
+                            context.grid.rows() + GRID_STEP,
+                            context.grid.cols() + GRID_STEP,
+                        );
+                    }
+                }
+                if is_key_pressed(KeyCode::Minus) {
+                    if shift {
+                        context.set_move_cost(context.options.orthogonal_cost - 1);
+                    } else {
+                        context.resize_grid(
+                            context.grid.rows().saturating_sub(GRID_STEP),
+                            context.grid.cols().saturating_sub(GRID_STEP),
+                        );
+                    }
+                }
+            }
+            ControlState::Placing(endpoint) => {
+                let key = match endpoint {
+                    Endpoint::Start => KeyCode::S,
+                    Endpoint::End => KeyCode::E,
+                };
+                // a second press of the key or a right click gives up
+                if is_key_pressed(key) || is_mouse_button_pressed(MouseButton::Right) {
+                    context.set_control_state(ControlState::Grid);
+                } else if let Some(cursor) = context.mouse_grid
+                    && is_mouse_button_pressed(MouseButton::Left)
+                {
+                    let pos = context.snap(cursor);
+                    context.report_snap(Some(cursor), Some(pos));
+                    match endpoint {
+                        Endpoint::Start => context.start = Some(pos),
+                        Endpoint::End => context.ends = vec![pos],
+                    }
+                    context.set_control_state(ControlState::Grid);
+                    context.calculate();
+                }
+            }
+            ControlState::Panning => 'l: {
+                if is_mouse_button_released(MouseButton::Middle) {
+                    context.set_control_state(ControlState::Grid);
+                    break 'l;
+                }
+
+                let delta = mouse_delta_position() / context.camera.zoom;
+                context.camera.target += delta;
+                context.view_moved |= delta != Vec2::ZERO;
+            }
+            ControlState::Scrubbing => 'l: {
+                let Some(replay_steps) = context.replay_steps else {
+                    context.set_control_state(ControlState::Grid);
+                    break 'l;
+                };
+                if !is_mouse_button_down(MouseButton::Left) {
+                    context.set_control_state(ControlState::Grid);
+                    break 'l;
+                }
+
+                let bar = scrub_bar();
+                let t = ((mouse_position().0 - bar.x) / bar.w).clamp(0.0, 1.0);
+                let steps = (t * replay_steps as f32).round() as u32;
+                if steps != context.steps_taken {
+                    context.scrub_to(steps);
+                }
+            }
+            ControlState::Drawing(paint) => 'l: {
+                if !is_mouse_button_down(MouseButton::Left)
+                    && !is_mouse_button_down(MouseButton::Right)
+                {
+                    context.set_control_state(ControlState::Grid);
+                    break 'l;
+                }
+
+                context.frames_since_paint += 1;
+                let Some(pos) = context.mouse_grid else {
+                    break 'l;
+                };
+                let (top_left, bottom_right) = context.brush_area(pos);
+                let (rows, cols) = (context.grid.rows(), context.grid.cols());
+                for r in top_left.0..=bottom_right.0 {
+                    for c in top_left.1..=bottom_right.1 {
+                        for pos in context.symmetry.images(rows, cols, Pos(r, c)) {
+                            if context.grid.cost(pos) != paint {
+                                context.grid.set_cost(pos, paint);
+                                context.mark_dirty();
+                                context.frames_since_paint = 0;
+                            }
+                        }
+                    }
+                }
+            }
+            ControlState::Rectangle {
+                anchor,
+                corner,
+                paint,
+            } => {
+                if !is_mouse_button_down(MouseButton::Left)
+                    && !is_mouse_button_down(MouseButton::Right)
+                {
+                    context.fill_rectangle(anchor, corner, paint);
+                    context.set_control_state(ControlState::Grid);
+                } else if let Some(pos) = context.mouse_grid {
+                    context.control_state = ControlState::Rectangle {
+                        anchor,
+                        corner: pos,
+                        paint,
+                    };
+                }
+            }
+            ControlState::Line { anchor, end, paint } => {
+                if !is_mouse_button_down(MouseButton::Left)
+                    && !is_mouse_button_down(MouseButton::Right)
+                {
+                    context.fill_line(anchor, end, paint);
+                    context.set_control_state(ControlState::Grid);
+                } else if let Some(pos) = context.mouse_grid {
+                    context.control_state = ControlState::Line {
+                        anchor,
+                        end: pos,
+                        paint,
+                    };
+                }
+            }
+        }
+
+        // a stroke recalculates once the cursor rests or the button is released
+        let drawing = matches!(context.control_state, ControlState::Drawing(_));
+        if context.dirty && (!drawing || context.frames_since_paint >= DRAW_DEBOUNCE_FRAMES) {
+            context.calculate();
+        }
+
+        if context.animate {
+            if !context.paused {
+                context.advance(context.speed);
+            }
+        } else if context.responsive {
+            context.advance(SEARCH_BUDGET);
+        }
+
+        if context.show_walker
+            && context.path_status == PathStatus::Found
+            && let Some(start) = context.start
+        {
+            let length = path_length(&context.grid, &context.options, start, &context.path);
+            context.walk_distance += context.walk_speed * get_frame_time();
+            if context.walk_distance > length {
+                context.walk_distance = if context.walk_loop && length > 0.0 {
+                    context.walk_distance % length
+                } else {
+                    length
+                };
+            }
+        }
+
+        context.clamp_camera();
+        set_camera(&context.camera);
+
+        if context.show_components && context.components.is_none() {
+            context.components = Some(pathfind::components(&context.grid, &context.options));
+        }
+        let components = context
+            .components
+            .as_ref()
+            .filter(|_| context.show_components);
+        if context.show_wall_distances && context.wall_distances.is_none() {
+            let distances = pathfind::wall_distances(&context.grid);
+            let max = distances.iter().flatten().copied().max().unwrap_or(0);
+            context.wall_distances = Some((distances, max));
+        }
+        let wall_distances = context
+            .wall_distances
+            .as_ref()
+            .filter(|_| context.show_wall_distances);
+
+        // hues run from blue at the start to red at the furthest discovered cell
+        let heatmap = is_key_down(KeyCode::H)
+            .then_some(context.search.as_ref())
+            .flatten()
+            .map(|search| {
+                let gscores: Vec<Option<u64>> = (0..context.grid.rows() as i64)
+                    .flat_map(|r| (0..context.grid.cols() as i64).map(move |c| Pos(r, c)))
+                    .map(|pos| search.gscore(&context.grid, pos))
+                    .collect();
+                let max = gscores.iter().flatten().copied().max().unwrap_or(0).max(1);
+                (gscores, max)
+            });
+
+        let topology = context.options.topology;
+        draw_walls(&context.grid, topology);
+        for r in 0..context.grid.rows() as i64 {
+            for c in 0..context.grid.cols() as i64 {
+                let pos = Pos(r, c);
+                if let Some(cost) = context.grid.cost(pos)
+                    && cost > 1
+                {
+                    draw_cell(topology, pos, terrain_color(cost));
+                }
+                // cells next to walls are red, turning blue and fainter further away
+                if let Some((distances, max)) = wall_distances
+                    && let Some(distance) = distances[context.grid.index(pos)]
+                    && distance > 0
+                {
+                    let t = distance as f32 / *max as f32;
+                    let color = Color::new(1.0 - t, 0.3, t, 0.6 * (1.0 - t) + 0.1);
+                    draw_cell(topology, pos, color);
+                }
+                if let Some((dr, dc)) = context.grid.exit(pos) {
+                    let center = cell_center(topology, pos);
+                    let forward = (cell_center(topology, pos + Pos(dr, dc)) - center) * 0.35;
+                    let side = vec2(-forward.y, forward.x);
+                    draw_triangle(
+                        center + forward,
+                        center - forward + side,
+                        center - forward - side,
+                        ARROW_COLOR,
+                    );
+                }
+                if (context.show_search || context.animate)
+                    && let Some(search) = &context.search
+                {
+                    if search.is_closed(&context.grid, pos) {
+                        draw_cell(topology, pos, CLOSED_COLOR);
+                    } else if search.is_open(&context.grid, pos) {
+                        draw_cell(topology, pos, OPEN_COLOR);
+                    }
+                }
+                if let Some((gscores, max)) = &heatmap
+                    && let Some(gscore) = gscores[context.grid.index(pos)]
+                {
+                    let t = gscore as f32 / *max as f32;
+                    draw_cell(topology, pos, Color::new(t, 0.2, 1.0 - t, 0.7));
+                }
+                if let Some((labels, _)) = components
+                    && let Some(component) = labels[context.grid.index(pos)]
+                {
+                    draw_cell(topology, pos, component_color(component));
+                }
+                if let Some(&reached) = context.reachable.get(context.grid.index(pos)) {
+                    let color = if reached {
+                        REACHABLE_COLOR
+                    } else {
+                        UNREACHABLE_COLOR
+                    };
+                    draw_cell(topology, pos, color);
+                }
+            }
+        }
+        let (rows, cols) = (context.grid.rows(), context.grid.cols());
+        let show_gridlines = match context.gridlines {
+            Gridlines::Auto => rows.max(cols) <= MAX_GRIDLINE_GRID_SIZE,
+            Gridlines::On => true,
+            Gridlines::Off => false,
+        };
+        if show_gridlines {
+            draw_grid_lines(topology, rows, cols, 0.05, WHITE);
+        }
+
+        // outline of the cells the brush would paint
+        if let Some(pos) = context.mouse_grid {
+            let (top_left, bottom_right) = context.brush_area(pos);
+            let (rows, cols) = (context.grid.rows(), context.grid.cols());
+            for (a, b) in context
+                .symmetry
+                .images(rows, cols, top_left)
+                .into_iter()
+                .zip(context.symmetry.images(rows, cols, bottom_right))
+            {
+                draw_area_lines(topology, a, b, 0.1, YELLOW);
+            }
+        }
+
+        for &(a, b) in context.grid.portals() {
+            let (a, b) = (cell_center(topology, a), cell_center(topology, b));
+            for p in [a, b] {
+                draw_circle(p.x, p.y, 0.35, PORTAL_COLOR);
+            }
+            draw_line(a.x, a.y, b.x, b.y, 0.03, PORTAL_COLOR);
+        }
+        if let Some(anchor) = context.portal_anchor {
+            let anchor = cell_center(topology, anchor);
+            draw_circle_lines(anchor.x, anchor.y, 0.35, 0.05, PORTAL_COLOR);
+        }
+
+        // rings on the last expansions, brightening towards the one that ended the search
+        if (context.show_search || context.animate)
+            && let Some(search) = &context.search
+        {
+            let recent: Vec<Pos> = search.recent_expansions().collect();
+            for (i, pos) in recent.iter().enumerate() {
+                let center = cell_center(topology, *pos);
+                let alpha = (i + 1) as f32 / recent.len() as f32 * 0.6;
+                let color = Color::new(1.0, 1.0, 1.0, alpha);
+                draw_circle_lines(center.x, center.y, 0.3, 0.04, color);
+            }
+        }
+        if context.path_status == PathStatus::Found
+            && let Some(goal) = context.goal
+        {
+            let center = cell_center(topology, goal);
+            draw_circle_lines(center.x, center.y, 0.42, 0.06, GOAL_RING_COLOR);
+        }
+
+        let cell_size = context.zoom * screen_height() / 2.0 * context.cell_size.min_element();
+        if cell_size >= SCORE_TEXT_MIN_CELL_SIZE
+            && let Some(search) = &context.search
+        {
+            for r in 0..context.grid.rows() as i64 {
+                for c in 0..context.grid.cols() as i64 {
+                    let pos = Pos(r, c);
+                    let (Some(gscore), Some(fscore)) = (
+                        search.gscore(&context.grid, pos),
+                        search.fscore(&context.grid, pos),
+                    ) else {
+                        continue;
+                    };
+                    let Vec2 { x, y } = cell_center(topology, pos);
+                    draw_text_centered(&format!("g {gscore}"), x, y - 0.25, 30.0, 0.008, WHITE);
+                    draw_text_centered(&format!("f {fscore}"), x, y + 0.25, 30.0, 0.008, WHITE);
+                    if context.show_order
+                        && let Some(index) = search.expansion_index(&context.grid, pos)
+                    {
+                        draw_text_centered(&format!("#{index}"), x, y, 30.0, 0.008, YELLOW);
+                    }
+                }
+            }
+        }
+
+        if let ControlState::Rectangle { anchor, corner, .. } = context.control_state {
+            draw_area_lines(topology, anchor, corner, 0.15, ORANGE);
+        }
+        if let ControlState::Line { anchor, end, .. } = context.control_state {
+            for pos in pathfind::bresenham(anchor, end) {
+                draw_cell_lines(topology, pos, 0.15, ORANGE);
+            }
+        }
+
+        if let Some(start) = context.start {
+            let center = cell_center(topology, start);
+            draw_text_centered("S", center.x, center.y, 50.0, 0.02, WHITE);
+
+            // later routes fade out; the best one is drawn as the path itself
+            for (i, (path, _)) in context.alternatives.iter().enumerate().skip(1) {
+                let fade = 1.0 - i as f32 / context.alternatives.len() as f32;
+                let color = Color {
+                    a: ALTERNATIVE_COLOR.a * fade,
+                    ..ALTERNATIVE_COLOR
+                };
+                draw_path(&context.grid, &context.options, start, path, 0.15, color);
+            }
+            if let Some(comparison) = &context.comparison
+                && let Some(path) = &comparison.path
+            {
+                draw_path(
+                    &context.grid,
+                    &context.options,
+                    start,
+                    path,
+                    0.25,
+                    COMPARE_COLOR,
+                );
+            }
+            // a responsive search shows its path once every segment is found
+            let path_ready = !(context.responsive && context.path_status == PathStatus::Searching);
+            match context.path_style {
+                _ if !path_ready => {}
+                PathStyle::Line => draw_path(
+                    &context.grid,
+                    &context.options,
+                    start,
+                    &context.path,
+                    0.1,
+                    GREEN,
+                ),
+                PathStyle::Cells => draw_path_cells(
+                    &context.grid,
+                    &context.options,
+                    start,
+                    &context.path,
+                    Color { a: 0.4, ..GREEN },
+                ),
+            }
+
+            if context.show_smoothed {
+                // jumps through portals or across edges stay gaps, as in the raw path
+                let jumps: Vec<(Pos, Pos)> = std::iter::once(start)
+                    .chain(context.path.iter().copied())
+                    .zip(context.path.iter().copied())
+                    .filter(|&(a, b)| is_jump(&context.grid, context.options.wrap, a, b))
+                    .collect();
+                let mut prev_point = start;
+                for &p in &context.smoothed_path {
+                    if !jumps.contains(&(prev_point, p)) {
+                        let (p1, p2) =
+                            (cell_center(topology, prev_point), cell_center(topology, p));
+                        draw_line(p1.x, p1.y, p2.x, p2.y, 0.1, SKYBLUE);
+                    }
+                    prev_point = p;
+                }
+            }
+        }
+        for (i, waypoint) in context.waypoints.iter().enumerate() {
+            let center = cell_center(topology, *waypoint);
+            draw_text_centered(&(i + 1).to_string(), center.x, center.y, 50.0, 0.02, WHITE);
+        }
+        for &end in &context.ends {
+            // with several ends, the one the path reaches stands out
+            let color = if context.ends.len() > 1 && context.goal == Some(end) {
+                GOLD
+            } else {
+                WHITE
+            };
+            let center = cell_center(topology, end);
+            draw_text_centered("E", center.x, center.y, 50.0, 0.02, color);
+        }
+
+        if context.show_walker
+            && context.path_status == PathStatus::Found
+            && let Some(start) = context.start
+        {
+            let walker = point_along_path(
+                &context.grid,
+                &context.options,
+                start,
+                &context.path,
+                context.walk_distance,
+            );
+            draw_circle(walker.x, walker.y, 0.3, WALKER_COLOR);
+        }
+
+        if context.show_sight
+            && has_lines_of_sight(topology)
+            && let (Some(start), Some(cursor)) = (context.start, context.mouse_grid)
+        {
+            let clear = if context.options.algorithm == Algorithm::ThetaStar {
+                context
+                    .options
+                    .theta_line_of_sight(&context.grid, start, cursor)
+            } else {
+                pathfind::line_of_sight(&context.grid, start, cursor)
+            };
+            let color = if clear { GREEN } else { RED };
+            for pos in pathfind::bresenham(start, cursor) {
+                let cell_color = if context.grid.is_passable(pos) {
+                    Color { a: 0.25, ..GREEN }
+                } else {
+                    Color { a: 0.5, ..RED }
+                };
+                draw_cell(topology, pos, cell_color);
+            }
+            let (p1, p2) = (cell_center(topology, start), cell_center(topology, cursor));
+            draw_line(p1.x, p1.y, p2.x, p2.y, 0.08, color);
+        }
+
+        draw_circle(0.0, 0.0, 0.1, RED);
+        draw_circle(mouse_pos_world.x, mouse_pos_world.y, 0.1, BLUE);
 
+        // UI
+        set_default_camera();
         draw_text(
-            &format!("[S] set start"),
+            &format!("{:?}", context.control_state),
             10.0,
-            screen_height() - 80.0,
             20.0,
-            WHITE,
-        );
-        draw_text(
-            &format!("[E] set end"),
-            10.0,
-            screen_height() - 60.0,
             20.0,
             WHITE,
         );
+        if context.animate && !context.open_history.is_empty() {
+            let (width, height) = (200.0, 40.0);
+            let x = screen_width() - width - 10.0;
+            draw_text("open set", x, 20.0, 20.0, WHITE);
+            draw_sparkline(&context.open_history, x, 30.0, width, height, SKYBLUE);
+        }
+        let minimap_scale = MINIMAP_SIZE / context.grid_extent().max_element();
+        let minimap_origin =
+            vec2(screen_width(), screen_height()) - context.grid_extent() * minimap_scale - 10.0;
+        draw_minimap(&context, minimap_origin, minimap_scale);
+        if context.show_legend {
+            draw_legend(
+                &legend_entries(&context, heatmap.is_some()),
+                screen_width() - 10.0,
+                80.0,
+            );
+        }
+        if let Some(replay_steps) = context.replay_steps {
+            draw_scrub_bar(context.steps_taken, replay_steps);
+        }
+        let status = match context.path_status {
+            PathStatus::Unset => None,
+            PathStatus::Searching => Some(("searching...", YELLOW)),
+            PathStatus::Found if context.path.is_empty() => Some(("start equals end", GRAY)),
+            PathStatus::Found if context.options.heuristic_overestimates() => Some((
+                "the heuristic overestimates, so the path may not be the cheapest",
+                ORANGE,
+            )),
+            PathStatus::Found => None,
+            PathStatus::NoPath(NoPathReason::StartUnset) => Some(("set a start", GRAY)),
+            PathStatus::NoPath(NoPathReason::EndUnset) => Some(("set an end", GRAY)),
+            PathStatus::NoPath(NoPathReason::StartBlocked) => {
+                Some(("NO PATH: the start is on a wall", RED))
+            }
+            PathStatus::NoPath(NoPathReason::EndBlocked) => {
+                Some(("NO PATH: the end is on a wall", RED))
+            }
+            PathStatus::NoPath(NoPathReason::WaypointBlocked) => {
+                Some(("NO PATH: a waypoint is on a wall", RED))
+            }
+            PathStatus::NoPath(NoPathReason::Disconnected) => {
+                Some(("NO PATH: the end cannot be reached", RED))
+            }
+            PathStatus::NoClearPath => Some(("NO PATH CLEAR OF WALLS", ORANGE)),
+        };
+        // a pending overwrite takes the status line, which nothing else is drawn over
+        let prompt = context.overwrite_pending.then(|| {
+            format!(
+                "{} exists: press Ctrl+S again to overwrite it",
+                context.map_path.display()
+            )
+        });
+        if let Some((text, color)) = prompt.as_deref().map(|text| (text, YELLOW)).or(status) {
+            draw_text(text, 10.0, 40.0, 20.0, color);
+        }
+        let mut stats = vec![
+            format!("cursor: {}", format_cell(context.mouse_grid, "off-grid")),
+            format!("start: {}", format_cell(context.start, "unset")),
+            match context.ends.as_slice() {
+                [] => "end: unset".to_owned(),
+                &[end] => format!("end: {}", format_cell(Some(end), "")),
+                ends => format!(
+                    "ends: {}, reached {}",
+                    ends.len(),
+                    format_cell(context.goal, "none")
+                ),
+            },
+            format!(
+                "arrows move: {}",
+                if context.nudge_end { "end" } else { "start" }
+            ),
+            format!("waypoints: {}", context.waypoints.len()),
+            format!("grid: {}x{}", context.grid.rows(), context.grid.cols()),
+            format!(
+                "cell size: {:.2}x{:.2}",
+                context.cell_size.x, context.cell_size.y
+            ),
+            match BRUSHES[context.brush] {
+                (name, None) => format!("brush: {name}, {0}x{0}", context.brush_size),
+                (name, Some(cost)) => {
+                    format!("brush: {name} (cost {cost}), {0}x{0}", context.brush_size)
+                }
+            },
+            format!("mirror drawing: {:?}", context.symmetry),
+            format!("path style: {:?}", context.path_style),
+            format!("gridlines: {:?}", context.gridlines),
+            format!(
+                "snap endpoints off walls: {}",
+                if context.snap_to_open { "on" } else { "off" }
+            ),
+            format!(
+                "fill density: {}%, cave smoothing: {}",
+                context.fill_density, context.cave_iterations
+            ),
+            format!("maze generator: {:?}", context.maze_generator),
+            format!("seed: {}", context.map_seed),
+            format!("pathlen: {:?}", context.path.len()),
+            format!("pathcost: {:?}", context.path_cost),
+            format!("numcalc: {:?}", context.stat_numcalc),
+            match &context.search {
+                Some(search) if !search.is_finished() => format!(
+                    "open set: {}, max {}",
+                    search.open_len(),
+                    context.stat_open_max
+                ),
+                _ => format!("open set: max {}", context.stat_open_max),
+            },
+            format!(
+                "time: {:.3} ms with open list {:?}",
+                context.stat_time_ms, context.options.open_list
+            ),
+            {
+                let frame_time =
+                    context.frame_times.iter().sum::<f32>() / context.frame_times.len() as f32;
+                format!(
+                    "fps: {:.0} ({:.1} ms/frame)",
+                    1.0 / frame_time,
+                    1000.0 * frame_time
+                )
+            },
+            if context.replanning {
+                "algorithm: D* Lite".to_owned()
+            } else {
+                format!("algorithm: {:?}", context.options.algorithm)
+            },
+            format!("heuristic: {:?}", context.options.heuristic),
+            format!("heuristic weight: {:.2}", context.options.heuristic_weight),
+            format!("clearance penalty: {}", context.options.clearance_weight),
+            format!("wall clearance: {}", context.options.clearance_radius),
+            format!("tie-break: {:?}", context.options.tie_break),
+            format!(
+                "connectivity: {} (step costs {}/{})",
+                match context.options.topology {
+                    Topology::Square4 => "4",
+                    Topology::Square8 => "8",
+                    Topology::Hex => "6, hex",
+                },
+                context.options.orthogonal_cost,
+                context.options.diagonal_cost
+            ),
+            format!("corner cutting: {:?}", context.options.corner_cutting),
+            format!("wrap: {:?}", context.options.wrap),
+            if context.show_walker {
+                format!(
+                    "walker: {} cells/s{}",
+                    context.walk_speed,
+                    if context.walk_loop { ", looping" } else { "" }
+                )
+            } else {
+                "walker: off".to_owned()
+            },
+            if context.animate {
+                format!(
+                    "animation: {}x{}",
+                    context.speed,
+                    if context.paused { " (paused)" } else { "" }
+                )
+            } else {
+                "animation: off".to_owned()
+            },
+            format!(
+                "search: {}",
+                if context.responsive {
+                    "responsive"
+                } else {
+                    "instant"
+                }
+            ),
+        ];
+        if let (Some(algorithm), Some(comparison)) =
+            (context.compare_algorithm, &context.comparison)
+        {
+            stats.push(match comparison.path {
+                Some(_) => format!(
+                    "compare {algorithm:?}: numcalc {}, pathcost {}",
+                    comparison.numcalc, comparison.cost
+                ),
+                None => format!(
+                    "compare {algorithm:?}: numcalc {}, no path",
+                    comparison.numcalc
+                ),
+            });
+        }
+        if let Some(full_numcalc) = context.stat_full_numcalc {
+            stats.push(format!("A* from scratch: numcalc {full_numcalc}"));
+        }
+        if context.path_count > 1 {
+            let costs: Vec<String> = context
+                .alternatives
+                .iter()
+                .map(|(_, cost)| cost.to_string())
+                .collect();
+            stats.push(format!(
+                "routes: {} of {}, costs {}",
+                costs.len(),
+                context.path_count,
+                costs.join(", ")
+            ));
+        }
+        if let Some(optimal_cost) = context.stat_optimal_cost
+            && context.path_status == PathStatus::Found
+        {
+            stats.push(if context.path_cost == optimal_cost {
+                "optimal: yes".to_owned()
+            } else {
+                format!("optimal: no (A* cost {optimal_cost})")
+            });
+        }
+        if let Some((_, count)) = components {
+            stats.push(format!("regions: {count}"));
+        }
+        for (i, line) in stats.iter().enumerate() {
+            draw_text(line, 10.0, 60.0 + 20.0 * i as f32, 20.0, WHITE);
+        }
+        if let Some(message) = &context.message {
+            draw_text(
+                message,
+                10.0,
+                60.0 + 20.0 * (stats.len() + 1) as f32,
+                20.0,
+                YELLOW,
+            );
+        }
+
+        if context.show_help {
+            let stats_width = stats
+                .iter()
+                .map(|line| measure_text(line, None, 20, 1.0).width)
+                .fold(0.0, f32::max);
+            draw_help(stats_width + 40.0, 60.0);
+        } else {
+            draw_text("[F1] key help", 10.0, screen_height() - 40.0, 20.0, GRAY);
+        }
+        if context.animate {
+            context.record_frame();
+        }
         next_frame().await;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scroll_zoom_takes_one_step_per_event() {
+        assert_eq!(scroll_zoom(0.1, 0.0), 0.1);
+        let (step_in, step_out) = (scroll_zoom(0.1, 1.0), scroll_zoom(0.1, -1.0));
+        assert!((step_in - 0.1 * ZOOM_STEP).abs() < 1e-6);
+        assert!((step_out - 0.1 / ZOOM_STEP).abs() < 1e-6);
+        for wheel_y in [3.0, 120.0, 1e6] {
+            assert_eq!(scroll_zoom(0.1, wheel_y), step_in);
+            assert_eq!(scroll_zoom(0.1, -wheel_y), step_out);
+        }
+        let partial = scroll_zoom(0.1, 0.5);
+        assert!(partial > 0.1 && partial < 0.1 * ZOOM_STEP);
+    }
+
+    #[test]
+    fn scroll_zoom_stays_within_limits() {
+        let (mut zoom_in, mut zoom_out) = (0.1, 0.1);
+        for _ in 0..200 {
+            zoom_in = scroll_zoom(zoom_in, 120.0);
+            zoom_out = scroll_zoom(zoom_out, -120.0);
+        }
+        assert_eq!(zoom_in, MAX_ZOOM);
+        assert_eq!(zoom_out, MIN_ZOOM);
+    }
+}
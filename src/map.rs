@@ -0,0 +1,346 @@
+//! Plain-text map files.
+//!
+//! ```text
+//! size 20 20
+//! start 3 4
+//! end -
+//! portal 0 0 5 7
+//! ....#...2.
+//! ```
+//!
+//! Each grid row is one line: `.` is plain ground, `#` a wall, `2`-`9` the cost of
+//! weighted terrain and `^`, `v`, `<`, `>` ground that can only be left in that direction.
+//! An unset endpoint is written as `-`, and several ends as consecutive `<row> <col>` pairs
+//! on the `end` line. Each optional `portal` line links two cells.
+//!
+//! Scripts can also give a bare grid without the header lines, sized by its rows, with `S`
+//! marking the start and `E` each end on open ground. A `*` is open ground too, so solutions
+//! exported with the path marked read back as the same grid:
+//!
+//! ```text
+//! S**#
+//! .#*E
+//! ```
+
+use std::fmt::Write as _;
+use std::path::Path;
+
+use crate::pathfind::{Grid, Pos};
+
+pub struct Map {
+    pub grid: Grid,
+    pub start: Option<Pos>,
+    pub ends: Vec<Pos>,
+}
+
+fn format_positions(positions: &[Pos]) -> String {
+    if positions.is_empty() {
+        return "-".to_owned();
+    }
+    positions
+        .iter()
+        .map(|Pos(r, c)| format!("{r} {c}"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+pub fn to_string(grid: &Grid, start: Option<Pos>, ends: &[Pos]) -> String {
+    let mut s = String::new();
+    writeln!(s, "size {} {}", grid.rows(), grid.cols()).unwrap();
+    let start: Vec<Pos> = start.into_iter().collect();
+    writeln!(s, "start {}", format_positions(&start)).unwrap();
+    writeln!(s, "end {}", format_positions(ends)).unwrap();
+    for &(a, b) in grid.portals() {
+        writeln!(s, "portal {}", format_positions(&[a, b])).unwrap();
+    }
+    for r in 0..grid.rows() as i64 {
+        for c in 0..grid.cols() as i64 {
+            s.push(match (grid.cost(Pos(r, c)), grid.exit(Pos(r, c))) {
+                (None, _) => '#',
+                (_, Some((-1, 0))) => '^',
+                (_, Some((1, 0))) => 'v',
+                (_, Some((0, -1))) => '<',
+                (_, Some(_)) => '>',
+                (Some(1), None) => '.',
+                (Some(cost), None) => char::from_digit(cost.min(9) as u32, 10).unwrap(),
+            });
+        }
+        s.push('\n');
+    }
+    s
+}
+
+pub fn save(path: &Path, grid: &Grid, start: Option<Pos>, ends: &[Pos]) -> std::io::Result<()> {
+    std::fs::write(path, to_string(grid, start, ends))
+}
+
+fn parse_header<'a>(line: Option<&'a str>, key: &str) -> Result<Vec<&'a str>, String> {
+    let line = line.ok_or_else(|| format!("missing `{key}` line"))?;
+    let mut words = line.split_whitespace();
+    if words.next() != Some(key) {
+        return Err(format!("expected `{key}`, found {line:?}"));
+    }
+    Ok(words.collect())
+}
+
+fn parse_number(word: &str) -> Result<u64, String> {
+    word.parse().map_err(|_| format!("invalid number {word:?}"))
+}
+
+fn parse_positions(line: Option<&str>, key: &str, grid: &Grid) -> Result<Vec<Pos>, String> {
+    let words = parse_header(line, key)?;
+    if words == ["-"] {
+        return Ok(Vec::new());
+    }
+    if words.is_empty() || words.len() % 2 != 0 {
+        return Err(format!("expected `{key} <row> <col>...` or `{key} -`"));
+    }
+
+    let mut positions = Vec::new();
+    for pair in words.chunks(2) {
+        let (r, c) = (pair[0], pair[1]);
+        let pos = Pos(parse_number(r)? as i64, parse_number(c)? as i64);
+        if !grid.contains(pos) {
+            return Err(format!("{key} {r} {c} is outside the grid"));
+        }
+        positions.push(pos);
+    }
+    Ok(positions)
+}
+
+pub fn parse(s: &str) -> Result<Map, String> {
+    let mut lines = s.lines();
+    let (rows, cols) = match parse_header(lines.next(), "size")?.as_slice() {
+        [rows, cols] => (parse_number(rows)?, parse_number(cols)?),
+        _ => return Err("expected `size <rows> <cols>`".to_owned()),
+    };
+    if rows == 0 || cols == 0 {
+        return Err("grid must not be empty".to_owned());
+    }
+    // every cell takes a character, so a size the text cannot hold is rejected before the
+    // grid is allocated for it
+    if rows
+        .checked_mul(cols)
+        .is_none_or(|cells| cells > s.len() as u64)
+    {
+        return Err(format!(
+            "size {rows} {cols} is larger than the rows that follow"
+        ));
+    }
+
+    let mut grid = Grid::new(rows, cols);
+    let start = match parse_positions(lines.next(), "start", &grid)?.as_slice() {
+        [] => None,
+        &[start] => Some(start),
+        _ => return Err("expected a single start".to_owned()),
+    };
+    let ends = parse_positions(lines.next(), "end", &grid)?;
+    let mut lines = lines.peekable();
+    while lines.peek().is_some_and(|line| line.starts_with("portal")) {
+        let (a, b) = match parse_positions(lines.next(), "portal", &grid)?.as_slice() {
+            &[a, b] if a != b => (a, b),
+            _ => return Err("expected `portal <row> <col> <row> <col>`".to_owned()),
+        };
+        if grid.portal_partner(a).is_some() || grid.portal_partner(b).is_some() {
+            return Err("portals must not share cells".to_owned());
+        }
+        grid.add_portal(a, b);
+    }
+    for r in 0..rows as i64 {
+        let line = lines
+            .next()
+            .ok_or_else(|| format!("expected {rows} rows, found {r}"))?;
+        if line.chars().count() != cols as usize {
+            return Err(format!("row {r} should have {cols} cells"));
+        }
+        for (c, ch) in line.chars().enumerate() {
+            let pos = Pos(r, c as i64);
+            match ch {
+                '#' => grid.set_cost(pos, None),
+                '.' => grid.set_cost(pos, Some(1)),
+                '2'..='9' => grid.set_cost(pos, ch.to_digit(10).map(u64::from)),
+                '^' => grid.set_exit(pos, Some((-1, 0))),
+                'v' => grid.set_exit(pos, Some((1, 0))),
+                '<' => grid.set_exit(pos, Some((0, -1))),
+                '>' => grid.set_exit(pos, Some((0, 1))),
+                _ => return Err(format!("unexpected {ch:?} in row {r}")),
+            }
+        }
+    }
+
+    Ok(Map { grid, start, ends })
+}
+
+/// Parses a bare grid of `.`, `*`, `#`, `S` and `E` rows.
+pub fn parse_ascii(s: &str) -> Result<Map, String> {
+    let lines: Vec<&str> = s.lines().filter(|line| !line.trim().is_empty()).collect();
+    let rows = lines.len() as u64;
+    let cols = lines.first().map_or(0, |line| line.chars().count()) as u64;
+    if rows == 0 || cols == 0 {
+        return Err("grid must not be empty".to_owned());
+    }
+
+    let mut grid = Grid::new(rows, cols);
+    let mut start = None;
+    let mut ends = Vec::new();
+    for (r, line) in lines.iter().enumerate() {
+        if line.chars().count() != cols as usize {
+            return Err(format!("row {r} should have {cols} cells"));
+        }
+        for (c, ch) in line.chars().enumerate() {
+            let pos = Pos(r as i64, c as i64);
+            match ch {
+                '#' => grid.set_cost(pos, None),
+                '.' | '*' => {}
+                'S' if start.is_none() => start = Some(pos),
+                'S' => return Err("expected a single start".to_owned()),
+                'E' => ends.push(pos),
+                _ => return Err(format!("unexpected {ch:?} in row {r}")),
+            }
+        }
+    }
+
+    Ok(Map { grid, start, ends })
+}
+
+pub fn load(path: &Path) -> Result<Map, String> {
+    let s = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+    parse(&s)
+}
+
+/// Builds a map from an image: dark pixels become walls and light ones open ground, a red
+/// pixel marks the start and a green one the end. Images with more than `max_size` pixels on
+/// a side are scaled down, each cell covering a block of pixels.
+pub fn load_image(path: &Path, max_size: u64) -> Result<Map, String> {
+    let image = image::open(path)
+        .map_err(|err| err.to_string())?
+        .into_rgb8();
+    let (width, height) = (image.width() as u64, image.height() as u64);
+    if width == 0 || height == 0 {
+        return Err("image is empty".to_owned());
+    }
+
+    let scale = (width.max(height) as f64 / max_size as f64).max(1.0);
+    let rows = ((height as f64 / scale).ceil() as u64).max(1);
+    let cols = ((width as f64 / scale).ceil() as u64).max(1);
+    let cell = |x: u32, y: u32| {
+        Pos(
+            (y as u64 * rows / height) as i64,
+            (x as u64 * cols / width) as i64,
+        )
+    };
+
+    let mut grid = Grid::new(rows, cols);
+    let mut brightness = vec![(0u64, 0u64); grid.len()];
+    let (mut start, mut end) = (None, None);
+    for (x, y, pixel) in image.enumerate_pixels() {
+        let [r, g, b] = pixel.0.map(u64::from);
+        let pos = cell(x, y);
+        let (sum, count) = &mut brightness[grid.index(pos)];
+        *sum += (r * 299 + g * 587 + b * 114) / 1000;
+        *count += 1;
+        if r > 200 && g < 80 && b < 80 {
+            start = Some(pos);
+        } else if g > 200 && r < 80 && b < 80 {
+            end = Some(pos);
+        }
+    }
+
+    for r in 0..rows as i64 {
+        for c in 0..cols as i64 {
+            let (sum, count) = brightness[grid.index(Pos(r, c))];
+            if sum < 128 * count {
+                grid.set_cost(Pos(r, c), None);
+            }
+        }
+    }
+    // the markers are colorful rather than light, so keep them open explicitly
+    for pos in start.iter().chain(&end) {
+        grid.set_cost(*pos, Some(1));
+    }
+
+    Ok(Map {
+        grid,
+        start,
+        ends: end.into_iter().collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAP: &str = "size 3 4\nstart 0 0\nend 2 3 0 3\nportal 1 0 1 3\n..#2\n.^#.\n9..>\n";
+
+    #[test]
+    fn maps_read_back_as_written() {
+        let map = parse(MAP).unwrap();
+        assert_eq!(map.start, Some(Pos(0, 0)));
+        assert_eq!(map.ends, [Pos(2, 3), Pos(0, 3)]);
+        assert_eq!(map.grid.portals(), [(Pos(1, 0), Pos(1, 3))]);
+        assert_eq!(map.grid.cost(Pos(0, 2)), None);
+        assert_eq!(map.grid.cost(Pos(2, 0)), Some(9));
+        assert_eq!(map.grid.exit(Pos(1, 1)), Some((-1, 0)));
+        assert_eq!(to_string(&map.grid, map.start, &map.ends), MAP);
+    }
+
+    #[test]
+    fn malformed_maps_are_errors() {
+        for s in [
+            "",
+            "size 0 4\nstart -\nend -\n",
+            "size 2 2\nstart 5 5\nend -\n..\n..\n",
+            "size 2 2\nstart -\nend -\n..\n",
+            "size 2 2\nstart -\nend -\n..\n.x\n",
+            "size 2 2\nstart -\nend -\n...\n..\n",
+        ] {
+            assert!(parse(s).is_err(), "{s:?}");
+        }
+    }
+
+    #[test]
+    fn oversized_headers_are_rejected_before_allocating() {
+        for size in [
+            "100000 100000",
+            "5000000000 5000000000",
+            "18446744073709551615 2",
+        ] {
+            let s = format!("size {size}\nstart -\nend -\n..\n");
+            assert!(parse(&s).is_err(), "{size}");
+        }
+    }
+
+    #[test]
+    fn bare_grids_mark_start_and_ends() {
+        let map = parse_ascii("S**#\n.#*E\n").unwrap();
+        assert_eq!((map.grid.rows(), map.grid.cols()), (2, 4));
+        assert_eq!(map.start, Some(Pos(0, 0)));
+        assert_eq!(map.ends, [Pos(1, 3)]);
+        assert_eq!(map.grid.cost(Pos(1, 1)), None);
+        assert!(parse_ascii("SS\n..\n").is_err());
+    }
+
+    #[test]
+    fn exported_solutions_read_back_as_the_same_grid() {
+        let mut grid = Grid::new(3, 5);
+        for wall in [Pos(0, 3), Pos(2, 1), Pos(1, 0)] {
+            grid.set_cost(wall, None);
+        }
+        let (start, ends) = (Some(Pos(0, 0)), [Pos(1, 4), Pos(2, 3)]);
+        let path = [Pos(0, 1), Pos(0, 2), Pos(1, 2), Pos(1, 3), Pos(1, 4)];
+
+        let map = parse_ascii(&crate::export::ascii(&grid, start, &ends, &path)).unwrap();
+        assert_eq!((map.grid.rows(), map.grid.cols()), (3, 5));
+        assert_eq!(map.start, start);
+        assert_eq!(map.ends, ends);
+        for r in 0..3 {
+            for c in 0..5 {
+                assert_eq!(
+                    map.grid.is_wall(Pos(r, c)),
+                    grid.is_wall(Pos(r, c)),
+                    "({r}, {c})"
+                );
+            }
+        }
+    }
+}
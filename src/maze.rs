@@ -0,0 +1,154 @@
+use crate::pathfind::{Grid, Pos};
+use crate::rng::Rng;
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Generator {
+    RecursiveBacktracker,
+    Prim,
+}
+
+impl Generator {
+    pub fn next(self) -> Self {
+        match self {
+            Generator::RecursiveBacktracker => Generator::Prim,
+            Generator::Prim => Generator::RecursiveBacktracker,
+        }
+    }
+
+    /// Carves a maze into `grid` and returns its two opposite corner cells.
+    pub fn generate(self, grid: &mut Grid, rng: &mut Rng) -> (Pos, Pos) {
+        match self {
+            Generator::RecursiveBacktracker => recursive_backtracker(grid, rng),
+            Generator::Prim => prim(grid, rng),
+        }
+    }
+}
+
+/// Carves a perfect maze into `grid` with a randomized depth-first search.
+///
+/// Passages run through the cells with even coordinates, so walls are one cell thick. The
+/// search follows each corridor as far as it goes, giving long winding paths with few
+/// dead ends. Returns the two opposite corner cells of the maze.
+pub fn recursive_backtracker(grid: &mut Grid, rng: &mut Rng) -> (Pos, Pos) {
+    for r in 0..grid.rows() as i64 {
+        for c in 0..grid.cols() as i64 {
+            grid.set_cost(Pos(r, c), None);
+        }
+    }
+
+    let start = Pos(0, 0);
+    grid.set_cost(start, Some(1));
+    let mut stack = vec![start];
+    while let Some(&curr) = stack.last() {
+        let unvisited: Vec<(i64, i64)> = [(-2, 0), (2, 0), (0, 2), (0, -2)]
+            .into_iter()
+            .filter(|&(dr, dc)| {
+                let next = curr + Pos(dr, dc);
+                grid.contains(next) && grid.is_wall(next)
+            })
+            .collect();
+        if unvisited.is_empty() {
+            stack.pop();
+            continue;
+        }
+
+        let (dr, dc) = unvisited[rng.below(unvisited.len())];
+        let next = curr + Pos(dr, dc);
+        grid.set_cost(curr + Pos(dr / 2, dc / 2), Some(1));
+        grid.set_cost(next, Some(1));
+        stack.push(next);
+    }
+
+    let last_row = (grid.rows() as i64 - 1) / 2 * 2;
+    let last_col = (grid.cols() as i64 - 1) / 2 * 2;
+    (start, Pos(last_row, last_col))
+}
+
+/// Carves a perfect maze into `grid` with randomized Prim's algorithm, using the same
+/// layout as `recursive_backtracker`.
+///
+/// The maze grows outwards from a corner at random points of its border instead of along one
+/// long corridor, so it has many short dead ends and paths that wind less.
+/// Returns the two opposite corner cells of the maze.
+pub fn prim(grid: &mut Grid, rng: &mut Rng) -> (Pos, Pos) {
+    for r in 0..grid.rows() as i64 {
+        for c in 0..grid.cols() as i64 {
+            grid.set_cost(Pos(r, c), None);
+        }
+    }
+
+    // adds `cell` to the maze and its unvisited neighbours to the frontier
+    fn carve(grid: &mut Grid, frontier: &mut Vec<(Pos, Pos)>, cell: Pos) {
+        grid.set_cost(cell, Some(1));
+        for (dr, dc) in [(-2, 0), (2, 0), (0, 2), (0, -2)] {
+            let next = cell + Pos(dr, dc);
+            if grid.contains(next) && grid.is_wall(next) {
+                frontier.push((next, cell + Pos(dr / 2, dc / 2)));
+            }
+        }
+    }
+
+    // cells next to the maze, each with the wall leading to it
+    let mut frontier = Vec::new();
+    let start = Pos(0, 0);
+    carve(grid, &mut frontier, start);
+    while !frontier.is_empty() {
+        let (cell, passage) = frontier.swap_remove(rng.below(frontier.len()));
+        if grid.is_wall(cell) {
+            grid.set_cost(passage, Some(1));
+            carve(grid, &mut frontier, cell);
+        }
+    }
+
+    let last_row = (grid.rows() as i64 - 1) / 2 * 2;
+    let last_col = (grid.cols() as i64 - 1) / 2 * 2;
+    (start, Pos(last_row, last_col))
+}
+
+/// Walls off the outermost ring of cells, leaving the inside as it is.
+pub fn wall_frame(grid: &mut Grid) {
+    let (last_row, last_col) = (grid.rows() as i64 - 1, grid.cols() as i64 - 1);
+    for r in 0..=last_row {
+        for c in 0..=last_col {
+            if r == 0 || c == 0 || r == last_row || c == last_col {
+                grid.set_cost(Pos(r, c), None);
+            }
+        }
+    }
+}
+
+/// Turns each cell into a wall with probability `density` percent and clears the rest,
+/// leaving the cells in `keep` open.
+pub fn random_fill(grid: &mut Grid, rng: &mut Rng, density: u32, keep: &[Pos]) {
+    for r in 0..grid.rows() as i64 {
+        for c in 0..grid.cols() as i64 {
+            let pos = Pos(r, c);
+            let is_wall = !keep.contains(&pos) && rng.below(100) < density as usize;
+            grid.set_cost(pos, if is_wall { None } else { Some(1) });
+        }
+    }
+}
+
+/// Grows organic caves: fills `grid` with walls at `density` percent, then smooths it
+/// `iterations` times. In each pass a cell becomes a wall when at least five of its eight
+/// neighbours are walls and opens up when at most three are, with cells beyond the edge
+/// counting as walls so that caves close off at the border.
+pub fn cave(grid: &mut Grid, rng: &mut Rng, density: u32, iterations: u32) {
+    random_fill(grid, rng, density, &[]);
+    for _ in 0..iterations {
+        let before = grid.clone();
+        for r in 0..grid.rows() as i64 {
+            for c in 0..grid.cols() as i64 {
+                let walls = (-1..=1)
+                    .flat_map(|dr| (-1..=1).map(move |dc| Pos(r + dr, c + dc)))
+                    .filter(|&pos| pos != Pos(r, c) && !before.is_passable(pos))
+                    .count();
+                if walls >= 5 {
+                    grid.set_cost(Pos(r, c), None);
+                } else if walls <= 3 {
+                    grid.set_cost(Pos(r, c), Some(1));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,2346 @@
+use std::borrow::Cow;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, VecDeque};
+
+pub const DEFAULT_ROWS: u64 = 20;
+pub const DEFAULT_COLS: u64 = 20;
+
+/// Cost of travelling through a portal, the same as one orthogonal step.
+const PORTAL_COST: u64 = 1;
+
+/// Step costs whose ratio approximates sqrt(2), so that diagonal paths are priced close to
+/// their true length while gscores stay integers.
+pub const SQRT2_ORTHOGONAL_COST: u64 = 10;
+pub const SQRT2_DIAGONAL_COST: u64 = 14;
+
+/// Theta* keeps its Euclidean scores in fixed point with this many units per cell.
+const THETA_SCALE: u64 = 1000;
+
+/// How many of its latest expansions each side of a search remembers.
+const RECENT_EXPANSIONS: usize = 8;
+
+const ORTHOGONAL_DIRECTIONS: [(i64, i64); 4] = [(-1, 0), (1, 0), (0, 1), (0, -1)];
+const ALL_DIRECTIONS: [(i64, i64); 8] = [
+    (-1, 0),
+    (1, 0),
+    (0, 1),
+    (0, -1),
+    (-1, -1),
+    (-1, 1),
+    (1, -1),
+    (1, 1),
+];
+const HEX_DIRECTIONS: [(i64, i64); 6] = [(-1, 0), (1, 0), (0, 1), (0, -1), (-1, 1), (1, -1)];
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Pos(pub i64, pub i64);
+
+impl Pos {
+    pub fn distance(&self, other: &Self) -> u64 {
+        self.0.abs_diff(other.0) + self.1.abs_diff(other.1)
+    }
+
+    pub fn chebyshev_distance(&self, other: &Self) -> u64 {
+        self.0.abs_diff(other.0).max(self.1.abs_diff(other.1))
+    }
+
+    pub fn euclidean_distance(&self, other: &Self) -> f64 {
+        let dr = self.0.abs_diff(other.0) as f64;
+        let dc = self.1.abs_diff(other.1) as f64;
+        dr.hypot(dc)
+    }
+
+    /// Number of steps between two cells of a hex grid in axial coordinates.
+    pub fn hex_distance(&self, other: &Self) -> u64 {
+        let dr = other.0 - self.0;
+        let dc = other.1 - self.1;
+        (dr.unsigned_abs() + dc.unsigned_abs() + (dr + dc).unsigned_abs()) / 2
+    }
+
+    /// Octile distance for steps costing `orthogonal_cost` and `diagonal_cost`.
+    pub fn octile_distance(&self, other: &Self, orthogonal_cost: u64, diagonal_cost: u64) -> u64 {
+        let dr = self.0.abs_diff(other.0);
+        let dc = self.1.abs_diff(other.1);
+        orthogonal_cost * (dr.max(dc) - dr.min(dc)) + diagonal_cost * dr.min(dc)
+    }
+}
+
+impl std::ops::Add<Pos> for Pos {
+    type Output = Pos;
+
+    fn add(self, rhs: Pos) -> Self::Output {
+        Self(self.0 + rhs.0, self.1 + rhs.1)
+    }
+}
+
+#[derive(Clone)]
+pub struct Grid {
+    rows: u64,
+    cols: u64,
+    /// Cost of entering each cell, `None` for walls.
+    cost: Vec<Option<u64>>,
+    /// The only direction each cell may be left in, `None` if unconstrained.
+    exits: Vec<Option<(i64, i64)>>,
+    /// Linked cell pairs; stepping onto either end allows moving to the other.
+    portals: Vec<(Pos, Pos)>,
+}
+
+impl Grid {
+    pub fn new(rows: u64, cols: u64) -> Self {
+        Self {
+            rows,
+            cols,
+            cost: vec![Some(1); (rows * cols) as usize],
+            exits: vec![None; (rows * cols) as usize],
+            portals: Vec::new(),
+        }
+    }
+
+    pub fn rows(&self) -> u64 {
+        self.rows
+    }
+
+    pub fn cols(&self) -> u64 {
+        self.cols
+    }
+
+    pub fn len(&self) -> usize {
+        self.cost.len()
+    }
+
+    pub fn index(&self, pos: Pos) -> usize {
+        pos.0 as usize * self.cols as usize + pos.1 as usize
+    }
+
+    pub fn contains(&self, pos: Pos) -> bool {
+        pos.0 >= 0 && pos.0 < self.rows as i64 && pos.1 >= 0 && pos.1 < self.cols as i64
+    }
+
+    pub fn cost(&self, pos: Pos) -> Option<u64> {
+        self.cost[self.index(pos)]
+    }
+
+    /// Sets the cost of entering `pos`, replacing any one-way constraint there.
+    pub fn set_cost(&mut self, pos: Pos, cost: Option<u64>) {
+        let index = self.index(pos);
+        self.cost[index] = cost;
+        self.exits[index] = None;
+    }
+
+    pub fn exit(&self, pos: Pos) -> Option<(i64, i64)> {
+        self.exits[self.index(pos)]
+    }
+
+    /// Makes `pos` plain ground that can only be left in `exit`, or lifts the constraint.
+    pub fn set_exit(&mut self, pos: Pos, exit: Option<(i64, i64)>) {
+        let index = self.index(pos);
+        self.cost[index] = Some(1);
+        self.exits[index] = exit;
+    }
+
+    pub fn has_one_way_cells(&self) -> bool {
+        self.exits.iter().any(Option::is_some)
+    }
+
+    pub fn is_wall(&self, pos: Pos) -> bool {
+        self.cost(pos).is_none()
+    }
+
+    pub fn is_passable(&self, pos: Pos) -> bool {
+        self.contains(pos) && !self.is_wall(pos)
+    }
+
+    /// Whether every open cell costs the same to enter.
+    pub fn is_uniform(&self) -> bool {
+        self.cost.iter().all(|cost| matches!(cost, None | Some(1)))
+    }
+
+    pub fn clear(&mut self) {
+        self.cost.fill(Some(1));
+        self.exits.fill(None);
+    }
+
+    /// Turns walls into plain ground and every open cell, weighted or one-way, into a wall.
+    pub fn invert(&mut self) {
+        for cost in &mut self.cost {
+            *cost = if cost.is_none() { Some(1) } else { None };
+        }
+        self.exits.fill(None);
+    }
+
+    /// Copy of this grid with the given dimensions, keeping the cells that still fit.
+    pub fn resized(&self, rows: u64, cols: u64) -> Self {
+        let mut grid = Self::new(rows, cols);
+        for r in 0..rows.min(self.rows) as i64 {
+            for c in 0..cols.min(self.cols) as i64 {
+                grid.set_cost(Pos(r, c), self.cost(Pos(r, c)));
+                if let Some(exit) = self.exit(Pos(r, c)) {
+                    grid.set_exit(Pos(r, c), Some(exit));
+                }
+            }
+        }
+        grid.portals = self
+            .portals
+            .iter()
+            .copied()
+            .filter(|&(a, b)| grid.contains(a) && grid.contains(b))
+            .collect();
+        grid
+    }
+
+    pub fn portals(&self) -> &[(Pos, Pos)] {
+        &self.portals
+    }
+
+    /// Links `a` and `b`. Both must be distinct cells that are not part of a portal yet.
+    pub fn add_portal(&mut self, a: Pos, b: Pos) {
+        debug_assert!(
+            a != b && self.portal_partner(a).is_none() && self.portal_partner(b).is_none()
+        );
+        self.portals.push((a, b));
+    }
+
+    /// Removes the portal with an end at `pos`, if any.
+    pub fn remove_portal(&mut self, pos: Pos) {
+        self.portals.retain(|&(a, b)| a != pos && b != pos);
+    }
+
+    /// The other end of the portal at `pos`.
+    pub fn portal_partner(&self, pos: Pos) -> Option<Pos> {
+        self.portals.iter().find_map(|&(a, b)| {
+            if a == pos {
+                Some(b)
+            } else if b == pos {
+                Some(a)
+            } else {
+                None
+            }
+        })
+    }
+
+    /// `pos` moved onto the grid by wrapping each coordinate around its axis.
+    fn wrapped(&self, pos: Pos) -> Pos {
+        Pos(
+            pos.0.rem_euclid(self.rows as i64),
+            pos.1.rem_euclid(self.cols as i64),
+        )
+    }
+
+    /// The copy of `to` closest to `from` when the grid repeats in every direction, so that
+    /// plain distances between the two measure the shortest way around the torus.
+    pub fn nearest_image(&self, from: Pos, to: Pos) -> Pos {
+        let nearest = |from: i64, to: i64, len: i64| {
+            let d = (to - from).rem_euclid(len);
+            if d * 2 > len {
+                from + d - len
+            } else {
+                from + d
+            }
+        };
+        Pos(
+            nearest(from.0, to.0, self.rows as i64),
+            nearest(from.1, to.1, self.cols as i64),
+        )
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Algorithm {
+    AStar,
+    Dijkstra,
+    Bfs,
+    Bidirectional,
+    /// Jump Point Search. Requires diagonal movement no dearer than two orthogonal steps and
+    /// uniform terrain, otherwise it expands cells like plain A*.
+    Jps,
+    /// Orders the frontier by the heuristic alone. Fast, but the path may not be optimal.
+    GreedyBestFirst,
+    /// Any-angle A* that links cells straight to their grandparent when it can see them.
+    /// Costs are Euclidean lengths scaled by terrain, ignoring the step costs.
+    ThetaStar,
+}
+
+/// Which cells count as neighbours.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Topology {
+    /// Square cells with orthogonal steps only.
+    Square4,
+    /// Square cells with orthogonal and diagonal steps.
+    Square8,
+    /// Hexagonal cells in axial coordinates: each row is shifted half a cell right of the one
+    /// above, so `(r, c)` touches `(r - 1, c + 1)` and `(r + 1, c - 1)` besides its four
+    /// orthogonal neighbours. All six steps cost `orthogonal_cost`, Theta* searches like A*
+    /// and JPS is not available.
+    Hex,
+}
+
+/// Distance estimate guiding the informed searches, in units of `orthogonal_cost`.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Heuristic {
+    /// The cheapest walk ignoring walls under the topology and step costs: Manhattan on 4-
+    /// connected squares, octile on 8-connected ones, hex steps on hexes and straight-line
+    /// distance for Theta*.
+    Auto,
+    /// Rows plus columns apart.
+    Manhattan,
+    /// Straight-line distance.
+    Euclidean,
+    /// The larger of rows and columns apart.
+    Chebyshev,
+    /// No estimate, which turns A* into Dijkstra.
+    Zero,
+}
+
+/// How the frontier orders cells with equal fscore.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum TieBreak {
+    /// Lowest row first, then lowest column.
+    Position,
+    /// Highest gscore first, i.e. the cell that got furthest along its path.
+    HighGscore,
+}
+
+impl TieBreak {
+    /// Secondary sort key of a cell reached with `gscore`; lower keys are expanded first.
+    fn key(self, gscore: u64) -> u64 {
+        match self {
+            TieBreak::Position => 0,
+            TieBreak::HighGscore => u64::MAX - gscore,
+        }
+    }
+}
+
+/// How the frontier of the cost-ordered searches is stored. Both expand cells in the same
+/// order; they differ only in speed.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum OpenList {
+    /// Binary heap, taking O(log n) per push and pop.
+    Heap,
+    /// Unsorted list scanned for the best cell on every pop, taking O(n) per pop.
+    Scan,
+}
+
+#[derive(Clone, Copy)]
+pub struct Options {
+    pub algorithm: Algorithm,
+    pub topology: Topology,
+    pub orthogonal_cost: u64,
+    pub diagonal_cost: u64,
+    pub corner_cutting: bool,
+    /// Factor applied to the heuristic. Values above 1 trade path optimality for fewer
+    /// expansions.
+    pub heuristic_weight: f64,
+    pub heuristic: Heuristic,
+    pub tie_break: TieBreak,
+    pub open_list: OpenList,
+    /// Moving off an edge enters the opposite edge, as on a torus.
+    pub wrap: bool,
+    /// Extra cost for entering a cell close to a wall, in orthogonal steps at distance 1 and
+    /// falling off with the inverse of the distance. Paths then keep to the middle of
+    /// corridors. Zero turns it off; Theta* ignores it.
+    pub clearance_weight: u64,
+    /// Open cells within this many steps of a wall, diagonals included, are kept out of the
+    /// path as if they were walls. Zero turns it off.
+    pub clearance_radius: u64,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            algorithm: Algorithm::AStar,
+            topology: Topology::Square4,
+            orthogonal_cost: 1,
+            diagonal_cost: 2,
+            corner_cutting: false,
+            heuristic_weight: 1.0,
+            heuristic: Heuristic::Auto,
+            tie_break: TieBreak::Position,
+            open_list: OpenList::Heap,
+            wrap: false,
+            clearance_weight: 0,
+            clearance_radius: 0,
+        }
+    }
+}
+
+impl Options {
+    /// The grid as the search sees it, with every cell within `clearance_radius` of a wall
+    /// walled off.
+    pub fn search_grid<'a>(&self, grid: &'a Grid) -> Cow<'a, Grid> {
+        if self.clearance_radius == 0 {
+            return Cow::Borrowed(grid);
+        }
+
+        let radius = self.clearance_radius as i64;
+        let offsets: Vec<Pos> = (-radius..=radius)
+            .flat_map(|dr| (-radius..=radius).map(move |dc| Pos(dr, dc)))
+            .filter(|offset| {
+                self.topology != Topology::Hex || offset.hex_distance(&Pos(0, 0)) <= radius as u64
+            })
+            .collect();
+        let mut inflated = grid.clone();
+        for r in 0..grid.rows() as i64 {
+            for c in 0..grid.cols() as i64 {
+                if !grid.is_wall(Pos(r, c)) {
+                    continue;
+                }
+                for &offset in &offsets {
+                    let pos = Pos(r, c) + offset;
+                    let pos = if self.wrap { grid.wrapped(pos) } else { pos };
+                    if grid.contains(pos) {
+                        inflated.set_cost(pos, None);
+                    }
+                }
+            }
+        }
+        Cow::Owned(inflated)
+    }
+
+    fn directions(&self) -> &'static [(i64, i64)] {
+        match self.topology {
+            Topology::Square4 => &ORTHOGONAL_DIRECTIONS,
+            Topology::Square8 => &ALL_DIRECTIONS,
+            Topology::Hex => &HEX_DIRECTIONS,
+        }
+    }
+
+    fn step_cost(&self, direction: (i64, i64)) -> u64 {
+        if self.topology == Topology::Square8 && direction.0 != 0 && direction.1 != 0 {
+            self.diagonal_cost
+        } else {
+            self.orthogonal_cost
+        }
+    }
+
+    /// Cost of entering `to` with a step in `direction`, scaled by the terrain of `to`.
+    fn move_cost(&self, grid: &Grid, to: Pos, direction: (i64, i64)) -> u64 {
+        self.step_cost(direction) * grid.cost(to).unwrap()
+    }
+
+    /// Penalty for entering `to`, given the distance of each cell to the nearest wall.
+    fn clearance_penalty(&self, grid: &Grid, clearance: &[Option<u64>], to: Pos) -> u64 {
+        match clearance.get(grid.index(to)) {
+            Some(&Some(distance)) if distance > 0 => {
+                self.clearance_weight * self.orthogonal_cost / distance
+            }
+            _ => 0,
+        }
+    }
+
+    /// Cell one step from `from` in `direction`, wrapping around the edges if enabled.
+    fn offset(&self, grid: &Grid, from: Pos, direction: (i64, i64)) -> Pos {
+        let to = from + Pos(direction.0, direction.1);
+        if self.wrap { grid.wrapped(to) } else { to }
+    }
+
+    /// Cell reached by stepping from `from` in `direction`, or `None` if the step is blocked.
+    fn neighbor(&self, grid: &Grid, from: Pos, direction: (i64, i64)) -> Option<Pos> {
+        if grid.exit(from).is_some_and(|exit| exit != direction) {
+            return None;
+        }
+        self.adjacent(grid, from, direction)
+    }
+
+    /// Like `neighbor`, but ignoring one-way cells so that adjacency goes both ways.
+    fn adjacent(&self, grid: &Grid, from: Pos, direction: (i64, i64)) -> Option<Pos> {
+        let to = self.offset(grid, from, direction);
+        if !grid.is_passable(to) {
+            return None;
+        }
+
+        // a diagonal step must not squeeze between two walls touching at a corner
+        let is_diagonal =
+            self.topology == Topology::Square8 && direction.0 != 0 && direction.1 != 0;
+        let squeezes = is_diagonal
+            && !self.corner_cutting
+            && !(grid.is_passable(self.offset(grid, from, (direction.0, 0)))
+                && grid.is_passable(self.offset(grid, from, (0, direction.1))));
+        (!squeezes).then_some(to)
+    }
+
+    /// Closest passable cell to `pos` in steps of the topology, across the edges if they wrap,
+    /// or `pos` itself if it is passable. The search steps over walls, so the cell found may
+    /// be cut off from `pos`.
+    pub fn nearest_passable(&self, grid: &Grid, pos: Pos) -> Option<Pos> {
+        if !grid.contains(pos) {
+            return None;
+        }
+        let mut seen = vec![false; grid.len()];
+        seen[grid.index(pos)] = true;
+        let mut queue = VecDeque::from([pos]);
+        while let Some(curr) = queue.pop_front() {
+            if grid.is_passable(curr) {
+                return Some(curr);
+            }
+            for &direction in self.directions() {
+                let next = self.offset(grid, curr, direction);
+                if grid.contains(next) && !seen[grid.index(next)] {
+                    seen[grid.index(next)] = true;
+                    queue.push_back(next);
+                }
+            }
+        }
+        None
+    }
+
+    /// Estimated cost from `pos` to the nearest of `targets`.
+    fn heuristic(&self, grid: &Grid, pos: Pos, targets: &[Pos]) -> u64 {
+        // a portal can lead anywhere, so no distance is a safe lower bound
+        if !grid.portals().is_empty() {
+            return 0;
+        }
+        targets
+            .iter()
+            .map(|&target| self.distance(grid, pos, target))
+            .min()
+            .unwrap_or(0)
+    }
+
+    fn distance(&self, grid: &Grid, pos: Pos, end: Pos) -> u64 {
+        if !self.heuristic_applies() {
+            return 0;
+        }
+        if self.heuristic == Heuristic::Auto && self.topology == Topology::Hex {
+            return self.orthogonal_cost * self.hex_distance(grid, pos, end);
+        }
+
+        let end = if self.wrap {
+            grid.nearest_image(pos, end)
+        } else {
+            end
+        };
+        let unit = if self.algorithm == Algorithm::ThetaStar {
+            THETA_SCALE
+        } else {
+            self.orthogonal_cost
+        } as f64;
+        match self.heuristic {
+            Heuristic::Auto => self.auto_distance(pos, end),
+            Heuristic::Manhattan => (pos.distance(&end) as f64 * unit) as u64,
+            Heuristic::Euclidean => (pos.euclidean_distance(&end) * unit) as u64,
+            Heuristic::Chebyshev => (pos.chebyshev_distance(&end) as f64 * unit) as u64,
+            Heuristic::Zero => 0,
+        }
+    }
+
+    /// The distance of [`Heuristic::Auto`] on square cells, `end` being its nearest image.
+    fn auto_distance(&self, pos: Pos, end: Pos) -> u64 {
+        match self.algorithm {
+            Algorithm::AStar
+            | Algorithm::Bidirectional
+            | Algorithm::Jps
+            | Algorithm::GreedyBestFirst => {
+                if self.topology != Topology::Square8 {
+                    self.orthogonal_cost * pos.distance(&end)
+                } else if self.diagonal_cost == self.orthogonal_cost {
+                    self.orthogonal_cost * pos.chebyshev_distance(&end)
+                } else {
+                    // a diagonal is never worth more than two orthogonal steps
+                    let diagonal_cost = self.diagonal_cost.min(2 * self.orthogonal_cost);
+                    pos.octile_distance(&end, self.orthogonal_cost, diagonal_cost)
+                }
+            }
+            Algorithm::ThetaStar => (pos.euclidean_distance(&end) * THETA_SCALE as f64) as u64,
+            Algorithm::Dijkstra | Algorithm::Bfs => 0,
+        }
+    }
+
+    /// Whether the algorithm is guided by a distance estimate at all.
+    fn heuristic_applies(&self) -> bool {
+        !matches!(self.algorithm, Algorithm::Dijkstra | Algorithm::Bfs)
+    }
+
+    /// Whether the heuristic can exceed the cost actually left, so that the path found may not
+    /// be the cheapest.
+    pub fn heuristic_overestimates(&self) -> bool {
+        if !self.heuristic_applies() {
+            return false;
+        }
+        // Theta* cuts hexes down to A*
+        let any_angle = self.algorithm == Algorithm::ThetaStar && self.topology != Topology::Hex;
+        let orthogonal_cost = self.orthogonal_cost as f64;
+        let diagonal_cost = self.diagonal_cost.min(2 * self.orthogonal_cost) as f64;
+        match (self.heuristic, self.topology) {
+            (Heuristic::Auto | Heuristic::Zero | Heuristic::Chebyshev, _) => false,
+            (_, Topology::Hex) => true,
+            (Heuristic::Manhattan, _) if any_angle => true,
+            (Heuristic::Euclidean, _) if any_angle => false,
+            (_, Topology::Square4) => false,
+            (Heuristic::Manhattan, Topology::Square8) => diagonal_cost < 2.0 * orthogonal_cost,
+            (Heuristic::Euclidean, Topology::Square8) => {
+                diagonal_cost < std::f64::consts::SQRT_2 * orthogonal_cost
+            }
+        }
+    }
+
+    /// Hex steps from `pos` to `end`, the shortest way round if the edges wrap.
+    fn hex_distance(&self, grid: &Grid, pos: Pos, end: Pos) -> u64 {
+        if !self.wrap {
+            return pos.hex_distance(&end);
+        }
+        // the nearest image on each axis alone can miss a shorter diagonal route
+        let (rows, cols) = (grid.rows() as i64, grid.cols() as i64);
+        let image = grid.nearest_image(pos, end);
+        (-1..=1)
+            .flat_map(|dr| (-1..=1).map(move |dc| Pos(image.0 + dr * rows, image.1 + dc * cols)))
+            .map(|image| pos.hex_distance(&image))
+            .min()
+            .unwrap()
+    }
+
+    /// Whether Theta* can link `from` straight to `to`, which unlike [`line_of_sight`] also
+    /// keeps to one-way exits, corner cutting and the wall clearance.
+    pub fn theta_line_of_sight(&self, grid: &Grid, from: Pos, to: Pos) -> bool {
+        self.line_cost(&self.search_grid(grid), from, to).is_some()
+    }
+
+    /// Highest terrain cost on the line from `from` to `to`, or `None` if a wall blocks it or
+    /// it squeezes between two walls touching at a corner without corner cutting.
+    fn line_cost(&self, grid: &Grid, from: Pos, to: Pos) -> Option<u64> {
+        let line = bresenham(from, to);
+        let mut cost = grid.cost(from)?;
+        for pair in line.windows(2) {
+            let (a, b) = (pair[0], pair[1]);
+            self.neighbor(grid, a, (b.0 - a.0, b.1 - a.1))?;
+            cost = cost.max(grid.cost(b)?);
+        }
+        Some(cost)
+    }
+
+    /// Priority of a cell reached with `gscore`.
+    fn fscore(&self, grid: &Grid, gscore: u64, pos: Pos, targets: &[Pos]) -> u64 {
+        let heuristic = self.heuristic(grid, pos, targets);
+        if self.algorithm == Algorithm::GreedyBestFirst {
+            heuristic
+        } else {
+            gscore + (heuristic as f64 * self.heuristic_weight).round() as u64
+        }
+    }
+}
+
+#[derive(PartialEq, Eq)]
+struct CellData {
+    pos: Pos,
+    fscore: u64,
+    // `TieBreak::key` of the cell
+    tie: u64,
+}
+
+/// Reversed so that `BinaryHeap` pops the lowest fscore first. Equal fscores fall back to the
+/// lower tie key, then to the lower position, which keeps the expansion order deterministic.
+impl Ord for CellData {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        other
+            .fscore
+            .cmp(&self.fscore)
+            .then_with(|| other.tie.cmp(&self.tie))
+            .then_with(|| other.pos.0.cmp(&self.pos.0))
+            .then_with(|| other.pos.1.cmp(&self.pos.1))
+    }
+}
+
+impl PartialOrd for CellData {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[derive(Clone)]
+pub struct SearchResult {
+    /// Cells from the one after `start` up to and including `end`, or `None` if unreachable.
+    pub path: Option<Vec<Pos>>,
+    /// Total movement cost of `path`, 0 if there is none.
+    pub cost: u64,
+    /// Number of cells expanded.
+    pub numcalc: u64,
+    /// The end the path leads to.
+    pub goal: Option<Pos>,
+}
+
+enum Frontier {
+    Heap(BinaryHeap<CellData>),
+    List(Vec<CellData>),
+    Queue(VecDeque<Pos>),
+}
+
+impl Frontier {
+    fn new(options: &Options) -> Self {
+        match (options.algorithm, options.open_list) {
+            (Algorithm::Bfs, _) => Frontier::Queue(VecDeque::new()),
+            (_, OpenList::Heap) => Frontier::Heap(BinaryHeap::new()),
+            (_, OpenList::Scan) => Frontier::List(Vec::new()),
+        }
+    }
+
+    fn push(&mut self, cell: CellData) {
+        match self {
+            Frontier::Heap(q) => q.push(cell),
+            Frontier::List(q) => q.push(cell),
+            Frontier::Queue(q) => q.push_back(cell.pos),
+        }
+    }
+
+    fn pop(&mut self) -> Option<Pos> {
+        match self {
+            Frontier::Heap(q) => q.pop().map(|cell| cell.pos),
+            Frontier::List(q) => {
+                // the greatest cell, as the heap would pop it; entries are never equal
+                // unless they are for the same cell, so the order matches exactly
+                let best = (0..q.len()).max_by(|&a, &b| q[a].cmp(&q[b]))?;
+                Some(q.swap_remove(best).pos)
+            }
+            Frontier::Queue(q) => q.pop_front(),
+        }
+    }
+}
+
+/// Frontier and scores of a search running in one direction.
+struct Side {
+    frontier: Frontier,
+    gscore: Vec<Option<u64>>,
+    parent: Vec<Option<Pos>>,
+    /// Whether a cell was expanded at its current gscore. Entries left in the frontier by
+    /// earlier, dearer relaxations are skipped, and a cheaper relaxation opens the cell again.
+    visited: Vec<bool>,
+    /// Whether a cell was ever expanded.
+    closed: Vec<bool>,
+    /// Number of cells discovered but not expanded yet.
+    open: usize,
+    /// Latest expanded cells, oldest first.
+    recent: VecDeque<Pos>,
+    tie_break: TieBreak,
+}
+
+impl Side {
+    fn new(options: &Options) -> Self {
+        Self {
+            frontier: Frontier::new(options),
+            gscore: Vec::new(),
+            parent: Vec::new(),
+            visited: Vec::new(),
+            closed: Vec::new(),
+            open: 0,
+            recent: VecDeque::new(),
+            tie_break: options.tie_break,
+        }
+    }
+
+    /// Empties the side for a new search while keeping its allocations.
+    fn reset(&mut self, options: &Options) {
+        match (&mut self.frontier, Frontier::new(options)) {
+            (Frontier::Heap(q), Frontier::Heap(_)) => q.clear(),
+            (Frontier::List(q), Frontier::List(_)) => q.clear(),
+            (Frontier::Queue(q), Frontier::Queue(_)) => q.clear(),
+            (_, frontier) => self.frontier = frontier,
+        }
+        self.gscore.clear();
+        self.parent.clear();
+        self.visited.clear();
+        self.closed.clear();
+        self.open = 0;
+        self.recent.clear();
+        self.tie_break = options.tie_break;
+    }
+
+    fn seed(&mut self, grid: &Grid, pos: Pos, fscore: u64) {
+        self.gscore.resize(grid.len(), None);
+        self.parent.resize(grid.len(), None);
+        self.visited.resize(grid.len(), false);
+        self.closed.resize(grid.len(), false);
+
+        if self.gscore[grid.index(pos)].is_none() {
+            self.open += 1;
+        }
+        self.gscore[grid.index(pos)] = Some(0);
+        self.frontier.push(CellData {
+            pos,
+            fscore,
+            tie: self.tie_break.key(0),
+        });
+    }
+
+    fn gscore(&self, grid: &Grid, pos: Pos) -> Option<u64> {
+        self.gscore.get(grid.index(pos)).copied().flatten()
+    }
+
+    fn is_closed(&self, grid: &Grid, pos: Pos) -> bool {
+        self.closed.get(grid.index(pos)).copied().unwrap_or(false)
+    }
+
+    /// Takes the next cell to expand and marks it closed.
+    fn pop(&mut self, grid: &Grid) -> Option<Pos> {
+        loop {
+            let pos = self.frontier.pop()?;
+            if !self.visited[grid.index(pos)] {
+                if !self.closed[grid.index(pos)] {
+                    self.open -= 1;
+                }
+                self.visited[grid.index(pos)] = true;
+                self.closed[grid.index(pos)] = true;
+                if self.recent.len() == RECENT_EXPANSIONS {
+                    self.recent.pop_front();
+                }
+                self.recent.push_back(pos);
+                return Some(pos);
+            }
+        }
+    }
+
+    /// Lowest fscore waiting in the frontier, discarding entries that were already expanded.
+    fn min_fscore(&mut self, grid: &Grid) -> Option<u64> {
+        match &mut self.frontier {
+            Frontier::Heap(q) => {
+                while let Some(cell) = q.peek() {
+                    if !self.visited[grid.index(cell.pos)] {
+                        return Some(cell.fscore);
+                    }
+                    q.pop();
+                }
+                None
+            }
+            Frontier::List(q) => {
+                q.retain(|cell| !self.visited[grid.index(cell.pos)]);
+                q.iter().map(|cell| cell.fscore).min()
+            }
+            Frontier::Queue(_) => None,
+        }
+    }
+
+    fn relax(&mut self, grid: &Grid, from: Pos, next_pos: Pos, tentative_gscore: u64, fscore: u64) {
+        if let Frontier::Queue(q) = &mut self.frontier {
+            if self.gscore[grid.index(next_pos)].is_none() {
+                self.open += 1;
+                self.gscore[grid.index(next_pos)] = Some(tentative_gscore);
+                self.parent[grid.index(next_pos)] = Some(from);
+                q.push_back(next_pos);
+            }
+            return;
+        }
+
+        // the parent follows the cheapest gscore seen so far; bidirectional search relies on
+        // both directions holding exact costs when they meet
+        let next_gscore = self.gscore[grid.index(next_pos)];
+        if next_gscore.is_none() || tentative_gscore < next_gscore.unwrap() {
+            if next_gscore.is_none() {
+                self.open += 1;
+            }
+            self.gscore[grid.index(next_pos)] = Some(tentative_gscore);
+            self.parent[grid.index(next_pos)] = Some(from);
+
+            self.frontier.push(CellData {
+                pos: next_pos,
+                fscore,
+                tie: self.tie_break.key(tentative_gscore),
+            });
+            self.visited[grid.index(next_pos)] = false;
+        }
+    }
+
+    /// Cells on the way from `root` to `pos`, excluding `root`.
+    fn path_to(&self, grid: &Grid, root: Pos, pos: Pos) -> Vec<Pos> {
+        let mut path = Vec::new();
+        let mut p = pos;
+        while p != root {
+            path.push(p);
+            p = self.parent[grid.index(p)].unwrap();
+        }
+
+        path.reverse();
+        path
+    }
+}
+
+/// A search that can be advanced one expansion at a time.
+pub struct Search {
+    start: Pos,
+    /// Goals of the search; it stops at whichever is reached first.
+    ends: Vec<Pos>,
+    options: Options,
+    forward: Side,
+    /// Search growing from `end`, only used by bidirectional A*.
+    backward: Option<Side>,
+    /// Best cell where both directions met so far, with the cost of the path through it.
+    meet: Option<(Pos, u64)>,
+    backward_turn: bool,
+    /// Whether the forward side expands jump points instead of neighboring cells.
+    jump_points: bool,
+    /// Distance of each cell to the nearest wall, only filled in for a clearance penalty.
+    clearance: Vec<Option<u64>>,
+    /// Largest number of open cells at any point of the search so far.
+    open_max: usize,
+    /// When each cell was expanded, counting from 1, indexed like the grid. A cell expanded
+    /// again keeps its latest number.
+    expansion_order: Vec<Option<u32>>,
+    /// The grid with the cells near walls walled off, if the options keep a clearance.
+    inflated: Option<Grid>,
+    /// Cells and steps a plain search must avoid, indexed like the grid; the spur searches of
+    /// `k_shortest_paths` set them to branch off earlier paths.
+    excluded: Vec<bool>,
+    forbidden_steps: Vec<(Pos, Pos)>,
+    finished: bool,
+    result: SearchResult,
+}
+
+impl Search {
+    pub fn new(grid: &Grid, start: Pos, ends: &[Pos], options: &Options) -> Self {
+        let mut search = Self {
+            start,
+            ends: Vec::new(),
+            options: *options,
+            forward: Side::new(options),
+            backward: None,
+            meet: None,
+            backward_turn: false,
+            jump_points: false,
+            clearance: Vec::new(),
+            open_max: 0,
+            expansion_order: Vec::new(),
+            inflated: None,
+            excluded: Vec::new(),
+            forbidden_steps: Vec::new(),
+            finished: true,
+            result: SearchResult {
+                path: None,
+                cost: 0,
+                numcalc: 0,
+                goal: None,
+            },
+        };
+        search.restart(grid, start, ends, options);
+        search
+    }
+
+    /// Starts over with new endpoints and options, reusing the buffers of the previous run.
+    pub fn restart(&mut self, grid: &Grid, start: Pos, ends: &[Pos], options: &Options) {
+        let inflated = match options.search_grid(grid) {
+            Cow::Borrowed(_) => None,
+            Cow::Owned(inflated) => Some(inflated),
+        };
+        self.reset(inflated.as_ref().unwrap_or(grid), start, ends, options);
+        self.inflated = inflated;
+    }
+
+    fn reset(&mut self, grid: &Grid, start: Pos, ends: &[Pos], options: &Options) {
+        let mut options = *options;
+        if options.topology == Topology::Hex && options.algorithm == Algorithm::ThetaStar {
+            options.algorithm = Algorithm::AStar;
+        }
+        let options = &options;
+        self.start = start;
+        self.ends.clear();
+        self.ends
+            .extend(ends.iter().copied().filter(|&end| grid.is_passable(end)));
+        self.options = *options;
+        self.meet = None;
+        self.backward_turn = false;
+        // scans along a wrapped row could circle the grid forever
+        self.jump_points = options.algorithm == Algorithm::Jps
+            && options.topology == Topology::Square8
+            && options.diagonal_cost <= 2 * options.orthogonal_cost
+            && !options.wrap
+            && grid.portals().is_empty()
+            && !grid.has_one_way_cells()
+            && grid.is_uniform()
+            && options.clearance_weight == 0;
+        self.clearance.clear();
+        if options.clearance_weight > 0 {
+            self.clearance = wall_distances(grid);
+        }
+        self.open_max = 0;
+        self.expansion_order.clear();
+        self.expansion_order.resize(grid.len(), None);
+        self.excluded.clear();
+        self.forbidden_steps.clear();
+        self.finished = true;
+        self.result = SearchResult {
+            path: None,
+            cost: 0,
+            numcalc: 0,
+            goal: None,
+        };
+        self.forward.reset(options);
+        if options.algorithm == Algorithm::Bidirectional {
+            let backward = self.backward.get_or_insert_with(|| Side::new(options));
+            backward.reset(options);
+        } else {
+            self.backward = None;
+        }
+
+        // an unreachable endpoint is settled before anything is queued
+        if !grid.is_passable(start) || self.ends.is_empty() {
+            return;
+        }
+
+        // standing on an end already is a path of no steps, with nothing to expand
+        if self.ends.contains(&start) {
+            self.result.path = Some(Vec::new());
+            self.result.goal = Some(start);
+            return;
+        }
+
+        self.finished = false;
+        self.forward
+            .seed(grid, start, options.heuristic(grid, start, &self.ends));
+        if let Some(backward) = &mut self.backward {
+            for &end in &self.ends {
+                backward.seed(grid, end, options.distance(grid, end, start));
+            }
+        }
+        self.open_max = self.open_len();
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    pub fn result(&self) -> &SearchResult {
+        &self.result
+    }
+
+    fn sides(&self) -> impl Iterator<Item = &Side> {
+        std::iter::once(&self.forward).chain(&self.backward)
+    }
+
+    /// Number of cells discovered but not expanded yet.
+    pub fn open_len(&self) -> usize {
+        self.sides().map(|side| side.open).sum()
+    }
+
+    pub fn open_max(&self) -> usize {
+        self.open_max
+    }
+
+    /// Whether `pos` has already been expanded.
+    pub fn is_closed(&self, grid: &Grid, pos: Pos) -> bool {
+        self.sides().any(|side| side.is_closed(grid, pos))
+    }
+
+    /// Where `pos` came in the order of expansion, counting from 1.
+    pub fn expansion_index(&self, grid: &Grid, pos: Pos) -> Option<u32> {
+        self.expansion_order.get(grid.index(pos)).copied().flatten()
+    }
+
+    /// Whether `pos` has been discovered but not expanded yet.
+    pub fn is_open(&self, grid: &Grid, pos: Pos) -> bool {
+        !self.is_closed(grid, pos) && self.sides().any(|side| side.gscore(grid, pos).is_some())
+    }
+
+    /// The last few cells each direction expanded, oldest first. Unless both directions met,
+    /// the very last one is the goal that ended the search.
+    pub fn recent_expansions(&self) -> impl Iterator<Item = Pos> + '_ {
+        self.sides().flat_map(|side| side.recent.iter().copied())
+    }
+
+    /// `score` in the same units as the result cost.
+    fn unscaled(&self, score: u64) -> u64 {
+        if self.options.algorithm == Algorithm::ThetaStar {
+            (score + THETA_SCALE / 2) / THETA_SCALE
+        } else {
+            score
+        }
+    }
+
+    /// Cheapest known cost from the start to `pos`.
+    pub fn gscore(&self, grid: &Grid, pos: Pos) -> Option<u64> {
+        Some(self.unscaled(self.forward.gscore(grid, pos)?))
+    }
+
+    /// Priority `pos` has in the frontier with its current gscore.
+    pub fn fscore(&self, grid: &Grid, pos: Pos) -> Option<u64> {
+        let gscore = self.forward.gscore(grid, pos)?;
+        Some(self.unscaled(self.options.fscore(grid, gscore, pos, &self.ends)))
+    }
+
+    /// Keeps the search off `cells` and the steps in `forbidden_steps` until it restarts. Only
+    /// plain searches honor them, not JPS, Theta* or bidirectional A*.
+    fn exclude(&mut self, grid: &Grid, cells: &[Pos], forbidden_steps: Vec<(Pos, Pos)>) {
+        self.excluded = vec![false; grid.len()];
+        for &pos in cells {
+            self.excluded[grid.index(pos)] = true;
+        }
+        self.forbidden_steps = forbidden_steps;
+    }
+
+    fn is_excluded(&self, grid: &Grid, from: Pos, to: Pos) -> bool {
+        self.excluded.get(grid.index(to)).copied().unwrap_or(false)
+            || self.forbidden_steps.contains(&(from, to))
+    }
+
+    pub fn run(&mut self, grid: &Grid) {
+        while !self.step(grid) {}
+    }
+
+    /// Expands a single cell. Returns `true` once the search is finished.
+    pub fn step(&mut self, grid: &Grid) -> bool {
+        let inflated = self.inflated.take();
+        let finished = self.expand(inflated.as_ref().unwrap_or(grid));
+        self.inflated = inflated;
+        self.open_max = self.open_max.max(self.open_len());
+        finished
+    }
+
+    fn expand(&mut self, grid: &Grid) -> bool {
+        if self.finished {
+            return true;
+        }
+
+        if self.backward.is_some() {
+            return self.step_bidirectional(grid);
+        }
+        if self.jump_points {
+            return self.step_jump_points(grid);
+        }
+        if self.options.algorithm == Algorithm::ThetaStar {
+            return self.step_theta(grid);
+        }
+
+        let Some(curr) = self.forward.pop(grid) else {
+            self.finished = true;
+            return true;
+        };
+
+        self.result.numcalc += 1;
+        self.expansion_order[grid.index(curr)] = Some(self.result.numcalc as u32);
+        if self.ends.contains(&curr) {
+            self.result.path = Some(self.forward.path_to(grid, self.start, curr));
+            self.result.cost = self.forward.gscore(grid, curr).unwrap();
+            self.result.goal = Some(curr);
+            self.finished = true;
+            return true;
+        }
+
+        let options = self.options;
+        for &direction in options.directions() {
+            let Some(next_pos) = options.neighbor(grid, curr, direction) else {
+                continue;
+            };
+            if self.is_excluded(grid, curr, next_pos) {
+                continue;
+            }
+
+            let tentative_gscore = self.forward.gscore(grid, curr).unwrap()
+                + options.move_cost(grid, next_pos, direction)
+                + options.clearance_penalty(grid, &self.clearance, next_pos);
+            let fscore = options.fscore(grid, tentative_gscore, next_pos, &self.ends);
+            self.forward
+                .relax(grid, curr, next_pos, tentative_gscore, fscore);
+        }
+        if let Some(partner) = grid.portal_partner(curr)
+            && grid.is_passable(partner)
+            && !self.is_excluded(grid, curr, partner)
+        {
+            let tentative_gscore =
+                self.forward.gscore(grid, curr).unwrap() + PORTAL_COST * options.orthogonal_cost;
+            let fscore = options.fscore(grid, tentative_gscore, partner, &self.ends);
+            self.forward
+                .relax(grid, curr, partner, tentative_gscore, fscore);
+        }
+
+        false
+    }
+
+    /// Expands a cell of Theta*, letting each neighbor skip `curr` when its parent is in view.
+    fn step_theta(&mut self, grid: &Grid) -> bool {
+        let Some(curr) = self.forward.pop(grid) else {
+            self.finished = true;
+            return true;
+        };
+
+        self.result.numcalc += 1;
+        self.expansion_order[grid.index(curr)] = Some(self.result.numcalc as u32);
+        let curr_gscore = self.forward.gscore(grid, curr).unwrap();
+        if self.ends.contains(&curr) {
+            // the parents are the corners of the path
+            self.result.path = Some(self.forward.path_to(grid, self.start, curr));
+            self.result.cost = self.unscaled(curr_gscore);
+            self.result.goal = Some(curr);
+            self.finished = true;
+            return true;
+        }
+
+        let options = self.options;
+        // straight lines ignore wrapping edges and one-way cells, so only grid steps are safe
+        let any_angle = !options.wrap && !grid.has_one_way_cells();
+        let parent = self.forward.parent[grid.index(curr)].filter(|_| any_angle);
+        let euclidean = |from: Pos, to: Pos, cost: u64| {
+            (from.euclidean_distance(&to) * (cost * THETA_SCALE) as f64).round() as u64
+        };
+        for &direction in &ALL_DIRECTIONS {
+            let Some(next_pos) = options.neighbor(grid, curr, direction) else {
+                continue;
+            };
+
+            let step = euclidean(curr, options.offset(grid, curr, direction), 1)
+                * grid.cost(next_pos).unwrap();
+            let tentative_gscore = curr_gscore + step;
+            let fscore = options.fscore(grid, tentative_gscore, next_pos, &self.ends);
+            self.forward
+                .relax(grid, curr, next_pos, tentative_gscore, fscore);
+
+            if let Some(parent) = parent
+                && let Some(cost) = options.line_cost(grid, parent, next_pos)
+            {
+                let tentative_gscore =
+                    self.forward.gscore(grid, parent).unwrap() + euclidean(parent, next_pos, cost);
+                let fscore = options.fscore(grid, tentative_gscore, next_pos, &self.ends);
+                self.forward
+                    .relax(grid, parent, next_pos, tentative_gscore, fscore);
+            }
+        }
+        if let Some(partner) = grid.portal_partner(curr)
+            && grid.is_passable(partner)
+        {
+            let tentative_gscore = curr_gscore + PORTAL_COST * THETA_SCALE;
+            let fscore = options.fscore(grid, tentative_gscore, partner, &self.ends);
+            self.forward
+                .relax(grid, curr, partner, tentative_gscore, fscore);
+        }
+
+        false
+    }
+
+    /// Expands the best jump point, scanning straight lines from it for the next ones.
+    fn step_jump_points(&mut self, grid: &Grid) -> bool {
+        let Some(curr) = self.forward.pop(grid) else {
+            self.finished = true;
+            return true;
+        };
+
+        self.result.numcalc += 1;
+        self.expansion_order[grid.index(curr)] = Some(self.result.numcalc as u32);
+        let curr_gscore = self.forward.gscore(grid, curr).unwrap();
+        if self.ends.contains(&curr) {
+            let jump_points = self.forward.path_to(grid, self.start, curr);
+            self.result.path = Some(fill_lines(self.start, &jump_points));
+            self.result.cost = curr_gscore;
+            self.result.goal = Some(curr);
+            self.finished = true;
+            return true;
+        }
+
+        let parent = self.forward.parent[grid.index(curr)].filter(|_| curr != self.start);
+        for direction in self.pruned_directions(grid, curr, parent) {
+            let Some(jump_point) = self.jump(grid, curr, direction) else {
+                continue;
+            };
+
+            let tentative_gscore = curr_gscore
+                + curr.octile_distance(
+                    &jump_point,
+                    self.options.orthogonal_cost,
+                    self.options.diagonal_cost,
+                );
+            let fscore = self
+                .options
+                .fscore(grid, tentative_gscore, jump_point, &self.ends);
+            self.forward
+                .relax(grid, curr, jump_point, tentative_gscore, fscore);
+        }
+
+        false
+    }
+
+    fn can_step(&self, grid: &Grid, from: Pos, direction: (i64, i64)) -> bool {
+        self.options.neighbor(grid, from, direction).is_some()
+    }
+
+    /// Directions worth scanning from `pos` when it was reached from `parent`.
+    fn pruned_directions(&self, grid: &Grid, pos: Pos, parent: Option<Pos>) -> Vec<(i64, i64)> {
+        let Some(parent) = parent else {
+            return ALL_DIRECTIONS.to_vec();
+        };
+
+        let d0 = (pos.0 - parent.0).signum();
+        let d1 = (pos.1 - parent.1).signum();
+        let open = |dr: i64, dc: i64| grid.is_passable(pos + Pos(dr, dc));
+        let mut directions = Vec::new();
+        if self.options.corner_cutting {
+            if d0 != 0 && d1 != 0 {
+                directions.extend([(d0, 0), (0, d1), (d0, d1)]);
+                if !open(-d0, 0) {
+                    directions.push((-d0, d1));
+                }
+                if !open(0, -d1) {
+                    directions.push((d0, -d1));
+                }
+            } else if d0 != 0 {
+                directions.push((d0, 0));
+                for side in [-1, 1] {
+                    if !open(0, side) {
+                        directions.push((d0, side));
+                    }
+                }
+            } else {
+                directions.push((0, d1));
+                for side in [-1, 1] {
+                    if !open(side, 0) {
+                        directions.push((side, d1));
+                    }
+                }
+            }
+        } else if d0 != 0 && d1 != 0 {
+            directions.extend([(d0, 0), (0, d1), (d0, d1)]);
+        } else {
+            // turning is always allowed since diagonals may not cut past the wall that
+            // forced it
+            let forward = (d0, d1);
+            let sides = if d0 != 0 {
+                [(0, -1), (0, 1)]
+            } else {
+                [(-1, 0), (1, 0)]
+            };
+            directions.push(forward);
+            for side in sides {
+                directions.push(side);
+                directions.push((forward.0 + side.0, forward.1 + side.1));
+            }
+        }
+
+        directions.retain(|&direction| self.can_step(grid, pos, direction));
+        directions
+    }
+
+    /// Scans from `from` in `direction` and returns the first jump point, if any.
+    fn jump(&self, grid: &Grid, from: Pos, direction: (i64, i64)) -> Option<Pos> {
+        let (d0, d1) = direction;
+        let mut pos = from;
+        loop {
+            if !self.can_step(grid, pos, direction) {
+                return None;
+            }
+            pos = pos + Pos(d0, d1);
+            if self.ends.contains(&pos) {
+                return Some(pos);
+            }
+
+            let open = |dr: i64, dc: i64| grid.is_passable(pos + Pos(dr, dc));
+            if d0 != 0 && d1 != 0 {
+                if self.options.corner_cutting
+                    && ((open(-d0, d1) && !open(-d0, 0)) || (open(d0, -d1) && !open(0, -d1)))
+                {
+                    return Some(pos);
+                }
+                if self.jump(grid, pos, (d0, 0)).is_some()
+                    || self.jump(grid, pos, (0, d1)).is_some()
+                {
+                    return Some(pos);
+                }
+            } else if self.options.corner_cutting {
+                let forced = if d0 != 0 {
+                    (open(d0, 1) && !open(0, 1)) || (open(d0, -1) && !open(0, -1))
+                } else {
+                    (open(1, d1) && !open(1, 0)) || (open(-1, d1) && !open(-1, 0))
+                };
+                if forced {
+                    return Some(pos);
+                }
+            } else {
+                let forced = if d0 != 0 {
+                    (open(0, 1) && !open(-d0, 1)) || (open(0, -1) && !open(-d0, -1))
+                } else {
+                    (open(1, 0) && !open(1, -d1)) || (open(-1, 0) && !open(-1, -d1))
+                };
+                if forced {
+                    return Some(pos);
+                }
+            }
+        }
+    }
+
+    /// Alternates expansions between the two directions until no cheaper meeting is possible.
+    fn step_bidirectional(&mut self, grid: &Grid) -> bool {
+        let Self {
+            start,
+            ends,
+            options,
+            forward,
+            backward: Some(backward),
+            meet,
+            backward_turn,
+            clearance,
+            ..
+        } = self
+        else {
+            unreachable!("bidirectional search without a backward side");
+        };
+
+        // every undiscovered route costs at least the lowest fscore of either frontier
+        if let Some((_, best)) = *meet
+            && (forward.min_fscore(grid).is_none_or(|f| f >= best)
+                || backward.min_fscore(grid).is_none_or(|f| f >= best))
+        {
+            return self.finish_bidirectional(grid);
+        }
+
+        let is_backward = *backward_turn;
+        *backward_turn = !*backward_turn;
+        let (side, other, targets) = if is_backward {
+            (backward, &*forward, std::slice::from_ref(start))
+        } else {
+            (forward, &*backward, ends.as_slice())
+        };
+
+        let Some(curr) = side.pop(grid) else {
+            return self.finish_bidirectional(grid);
+        };
+
+        self.result.numcalc += 1;
+        self.expansion_order[grid.index(curr)] = Some(self.result.numcalc as u32);
+        let curr_gscore = side.gscore(grid, curr).unwrap();
+        let mut edges = Vec::new();
+        for &direction in options.directions() {
+            let next_pos = options.offset(grid, curr, direction);
+            // the backward side walks edges in reverse, so it pays for entering `curr`
+            if is_backward {
+                let reverse = (-direction.0, -direction.1);
+                if grid.is_passable(next_pos) && options.neighbor(grid, next_pos, reverse).is_some()
+                {
+                    let cost = options.move_cost(grid, curr, reverse)
+                        + options.clearance_penalty(grid, clearance, curr);
+                    edges.push((next_pos, cost));
+                }
+            } else if options.neighbor(grid, curr, direction).is_some() {
+                let cost = options.move_cost(grid, next_pos, direction)
+                    + options.clearance_penalty(grid, clearance, next_pos);
+                edges.push((next_pos, cost));
+            }
+        }
+        // portals work both ways at the same cost
+        if let Some(partner) = grid.portal_partner(curr)
+            && grid.is_passable(partner)
+        {
+            edges.push((partner, PORTAL_COST * options.orthogonal_cost));
+        }
+
+        for (next_pos, cost) in edges {
+            let tentative_gscore = curr_gscore + cost;
+            let fscore = options.fscore(grid, tentative_gscore, next_pos, targets);
+            side.relax(grid, curr, next_pos, tentative_gscore, fscore);
+
+            if let (Some(a), Some(b)) = (side.gscore(grid, next_pos), other.gscore(grid, next_pos))
+                && meet.is_none_or(|(_, best)| a + b < best)
+            {
+                *meet = Some((next_pos, a + b));
+            }
+        }
+        if let Some(b) = other.gscore(grid, curr)
+            && meet.is_none_or(|(_, best)| curr_gscore + b < best)
+        {
+            *meet = Some((curr, curr_gscore + b));
+        }
+
+        false
+    }
+
+    fn finish_bidirectional(&mut self, grid: &Grid) -> bool {
+        if let (Some((meet, cost)), Some(backward)) = (self.meet, &self.backward) {
+            let mut path = self.forward.path_to(grid, self.start, meet);
+            // the backward parents lead from the meeting cell towards one of the ends
+            let mut p = meet;
+            while let Some(next) = backward.parent[grid.index(p)] {
+                p = next;
+                path.push(p);
+            }
+
+            self.result.path = Some(path);
+            self.result.cost = cost;
+            self.result.goal = Some(p);
+        }
+        self.finished = true;
+        true
+    }
+}
+
+/// Path from `start` to `end` with plain 4-connected A* and its cost, or `None` if there is
+/// none. The app steps a [`Search`] instead; this is the whole search in one call.
+#[cfg_attr(not(test), expect(dead_code))]
+pub fn astar(grid: &Grid, start: Pos, end: Pos) -> Option<(Vec<Pos>, u64)> {
+    let mut search = Search::new(grid, start, &[end], &Options::default());
+    search.run(grid);
+    let result = search.result();
+    Some((result.path.clone()?, result.cost))
+}
+
+/// Up to `k` cheapest loopless paths from `start` to the nearest of `ends`, cheapest first, each
+/// given like `SearchResult::path` along with its cost. Yen's algorithm branches each new path
+/// off an earlier one with an A* search that avoids the shared beginning and the steps the
+/// earlier paths took next.
+pub fn k_shortest_paths(
+    grid: &Grid,
+    start: Pos,
+    ends: &[Pos],
+    options: &Options,
+    k: usize,
+) -> Vec<(Vec<Pos>, u64)> {
+    // the clearance is applied once here instead of on every restart
+    let inflated = options.search_grid(grid);
+    let grid = &*inflated;
+    let options = Options {
+        algorithm: Algorithm::AStar,
+        heuristic_weight: 1.0,
+        clearance_radius: 0,
+        ..*options
+    };
+
+    // paths are kept with the start and the cost of reaching each of their cells
+    let mut search = Search::new(grid, start, ends, &options);
+    let mut search_from = |from: Pos, excluded: &[Pos], forbidden_steps: Vec<(Pos, Pos)>| {
+        search.restart(grid, from, ends, &options);
+        search.exclude(grid, excluded, forbidden_steps);
+        search.run(grid);
+        let path = search.result().path.clone()?;
+        let costs: Vec<u64> = path
+            .iter()
+            .map(|&pos| search.gscore(grid, pos).unwrap())
+            .collect();
+        Some((path, costs))
+    };
+
+    let Some((path, costs)) = search_from(start, &[], Vec::new()) else {
+        return Vec::new();
+    };
+    let mut found = vec![(
+        std::iter::once(start).chain(path).collect::<Vec<_>>(),
+        std::iter::once(0).chain(costs).collect::<Vec<_>>(),
+    )];
+    let mut candidates: Vec<(Vec<Pos>, Vec<u64>)> = Vec::new();
+    while found.len() < k {
+        let (prev, prev_costs) = found.last().unwrap().clone();
+        for j in 0..prev.len() - 1 {
+            let (spur, root) = (prev[j], &prev[..=j]);
+            let forbidden_steps = found
+                .iter()
+                .filter(|(cells, _)| cells.len() > j + 1 && cells[..=j] == *root)
+                .map(|(cells, _)| (spur, cells[j + 1]))
+                .collect();
+            let Some((path, costs)) = search_from(spur, &root[..j], forbidden_steps) else {
+                continue;
+            };
+
+            let cells: Vec<Pos> = root.iter().copied().chain(path).collect();
+            if found
+                .iter()
+                .chain(&candidates)
+                .any(|(other, _)| *other == cells)
+            {
+                continue;
+            }
+            let costs = prev_costs[..=j]
+                .iter()
+                .copied()
+                .chain(costs.iter().map(|cost| prev_costs[j] + cost))
+                .collect();
+            candidates.push((cells, costs));
+        }
+
+        let Some(best) = (0..candidates.len()).min_by_key(|&i| *candidates[i].1.last().unwrap())
+        else {
+            break;
+        };
+        found.push(candidates.remove(best));
+    }
+
+    found
+        .into_iter()
+        .map(|(cells, costs)| (cells[1..].to_vec(), *costs.last().unwrap()))
+        .collect()
+}
+
+/// Priority of a cell in D* Lite: the estimated cost of a route through it, then its own cost.
+type Key = (u64, u64);
+
+/// Cost of a cell D* Lite has not reached.
+const UNREACHED: u64 = u64::MAX;
+
+/// D* Lite, which searches from the ends back towards the start and keeps its scores between
+/// updates. When cells change or the start moves, only the cells whose cost to the nearest
+/// end is affected are expanded again. It searches like unweighted A* with the heuristic and
+/// step costs of the options, whatever algorithm they name.
+pub struct DStarLite {
+    start: Pos,
+    // start the heuristic was measured from when `km` was last raised
+    last_start: Pos,
+    ends: Vec<Pos>,
+    options: Options,
+    // the grid as of the last update, to find the cells edited since
+    grid: Grid,
+    clearance: Vec<Option<u64>>,
+    // cost from each cell to the nearest end, as of its last expansion
+    g: Vec<u64>,
+    // cost from each cell to the nearest end, looking one step ahead at the neighbors
+    rhs: Vec<u64>,
+    // key each queued cell is queued with; heap entries with any other key are stale
+    queued: Vec<Option<Key>>,
+    queue: BinaryHeap<Reverse<(Key, i64, i64)>>,
+    // added to every key as the start moves, so that keys already queued stay comparable
+    km: u64,
+    // `numcalc` counts the expansions of the latest update only
+    result: SearchResult,
+}
+
+impl DStarLite {
+    pub fn new(grid: &Grid, start: Pos, ends: &[Pos], options: &Options) -> Self {
+        let mut dstar = Self {
+            start,
+            last_start: start,
+            ends: ends.to_vec(),
+            options: Options {
+                algorithm: Algorithm::AStar,
+                heuristic_weight: 1.0,
+                ..*options
+            },
+            grid: options.search_grid(grid).into_owned(),
+            clearance: Vec::new(),
+            g: vec![UNREACHED; grid.len()],
+            rhs: vec![UNREACHED; grid.len()],
+            queued: vec![None; grid.len()],
+            queue: BinaryHeap::new(),
+            km: 0,
+            result: SearchResult {
+                path: None,
+                cost: 0,
+                numcalc: 0,
+                goal: None,
+            },
+        };
+        if options.clearance_weight > 0 {
+            dstar.clearance = wall_distances(&dstar.grid);
+        }
+        for &end in ends {
+            if dstar.grid.is_passable(end) {
+                dstar.rhs[grid.index(end)] = 0;
+                let key = dstar.key(end);
+                dstar.push(end, key);
+            }
+        }
+        dstar.compute();
+        dstar
+    }
+
+    /// Whether the scores can be carried over to a search with these ends and options on
+    /// `grid`. Other changes than edited cells or a moved start need a new search.
+    pub fn can_replan(&self, grid: &Grid, ends: &[Pos], options: &Options) -> bool {
+        let current = &self.options;
+        self.grid.rows() == grid.rows()
+            && self.grid.cols() == grid.cols()
+            && self.grid.portals() == grid.portals()
+            && self.ends == ends
+            && current.topology == options.topology
+            && current.heuristic == options.heuristic
+            && current.orthogonal_cost == options.orthogonal_cost
+            && current.diagonal_cost == options.diagonal_cost
+            && current.corner_cutting == options.corner_cutting
+            && current.wrap == options.wrap
+            && current.clearance_weight == options.clearance_weight
+            && current.clearance_radius == options.clearance_radius
+    }
+
+    /// Brings the path up to date with `grid` and a start that may have moved.
+    pub fn replan(&mut self, grid: &Grid, start: Pos) {
+        let grid = &*self.options.search_grid(grid);
+        let mut changed = Vec::new();
+        for r in 0..grid.rows() as i64 {
+            for c in 0..grid.cols() as i64 {
+                let pos = Pos(r, c);
+                if grid.cost(pos) != self.grid.cost(pos) || grid.exit(pos) != self.grid.exit(pos) {
+                    changed.push(pos);
+                }
+            }
+        }
+        self.grid = grid.clone();
+        if self.options.clearance_weight > 0 {
+            let clearance = wall_distances(grid);
+            for r in 0..grid.rows() as i64 {
+                for c in 0..grid.cols() as i64 {
+                    let index = grid.index(Pos(r, c));
+                    if clearance[index] != self.clearance[index] {
+                        changed.push(Pos(r, c));
+                    }
+                }
+            }
+            self.clearance = clearance;
+        }
+
+        if start != self.start {
+            self.km += self.heuristic(self.last_start, start);
+            self.last_start = start;
+            self.start = start;
+        }
+
+        // a changed cell alters the steps into and out of it, and the diagonal steps past it
+        for pos in changed {
+            for dr in -1..=1 {
+                for dc in -1..=1 {
+                    let next = self.options.offset(&self.grid, pos, (dr, dc));
+                    if self.grid.contains(next) {
+                        self.update(next);
+                    }
+                }
+            }
+            if let Some(partner) = self.grid.portal_partner(pos) {
+                self.update(partner);
+            }
+        }
+
+        self.result.numcalc = 0;
+        self.compute();
+    }
+
+    pub fn result(&self) -> &SearchResult {
+        &self.result
+    }
+
+    fn heuristic(&self, from: Pos, to: Pos) -> u64 {
+        self.options.heuristic(&self.grid, from, &[to])
+    }
+
+    fn key(&self, pos: Pos) -> Key {
+        let index = self.grid.index(pos);
+        let cost = self.g[index].min(self.rhs[index]);
+        (
+            cost.saturating_add(self.heuristic(self.start, pos) + self.km),
+            cost,
+        )
+    }
+
+    fn push(&mut self, pos: Pos, key: Key) {
+        self.queued[self.grid.index(pos)] = Some(key);
+        self.queue.push(Reverse((key, pos.0, pos.1)));
+    }
+
+    /// Lowest queued key and its cell, discarding stale heap entries.
+    fn top(&mut self) -> Option<(Key, Pos)> {
+        while let Some(&Reverse((key, r, c))) = self.queue.peek() {
+            if self.queued[self.grid.index(Pos(r, c))] == Some(key) {
+                return Some((key, Pos(r, c)));
+            }
+            self.queue.pop();
+        }
+        None
+    }
+
+    /// Cells one step from `pos`, with the cost of each step.
+    fn successors(&self, pos: Pos) -> Vec<(Pos, u64)> {
+        let options = &self.options;
+        let mut successors = Vec::new();
+        for &direction in options.directions() {
+            if let Some(next) = options.neighbor(&self.grid, pos, direction) {
+                let cost = options.move_cost(&self.grid, next, direction)
+                    + options.clearance_penalty(&self.grid, &self.clearance, next);
+                successors.push((next, cost));
+            }
+        }
+        if let Some(partner) = self.grid.portal_partner(pos)
+            && self.grid.is_passable(partner)
+        {
+            successors.push((partner, PORTAL_COST * options.orthogonal_cost));
+        }
+        successors
+    }
+
+    /// Cells that can step to `pos`.
+    fn predecessors(&self, pos: Pos) -> Vec<Pos> {
+        let options = &self.options;
+        let mut predecessors = Vec::new();
+        for &direction in options.directions() {
+            let from = options.offset(&self.grid, pos, (-direction.0, -direction.1));
+            if self.grid.is_passable(from)
+                && options.neighbor(&self.grid, from, direction) == Some(pos)
+            {
+                predecessors.push(from);
+            }
+        }
+        if let Some(partner) = self.grid.portal_partner(pos)
+            && self.grid.is_passable(partner)
+        {
+            predecessors.push(partner);
+        }
+        predecessors
+    }
+
+    /// Recomputes the lookahead cost of `pos` and queues it if it no longer matches.
+    fn update(&mut self, pos: Pos) {
+        let index = self.grid.index(pos);
+        self.rhs[index] = if !self.grid.is_passable(pos) {
+            UNREACHED
+        } else if self.ends.contains(&pos) {
+            0
+        } else {
+            self.successors(pos)
+                .into_iter()
+                .map(|(next, cost)| self.g[self.grid.index(next)].saturating_add(cost))
+                .min()
+                .unwrap_or(UNREACHED)
+        };
+        self.queued[index] = None;
+        if self.g[index] != self.rhs[index] {
+            let key = self.key(pos);
+            self.push(pos, key);
+        }
+    }
+
+    /// Expands cells until the cost of the start is settled, then follows it to an end.
+    fn compute(&mut self) {
+        let start_index = self.grid.index(self.start);
+        while let Some((key, pos)) = self.top() {
+            if key >= self.key(self.start) && self.rhs[start_index] == self.g[start_index] {
+                break;
+            }
+
+            self.queue.pop();
+            let index = self.grid.index(pos);
+            self.queued[index] = None;
+            self.result.numcalc += 1;
+            let new_key = self.key(pos);
+            if key < new_key {
+                self.push(pos, new_key);
+            } else if self.g[index] > self.rhs[index] {
+                self.g[index] = self.rhs[index];
+                for from in self.predecessors(pos) {
+                    self.update(from);
+                }
+            } else {
+                self.g[index] = UNREACHED;
+                self.update(pos);
+                for from in self.predecessors(pos) {
+                    self.update(from);
+                }
+            }
+        }
+
+        self.result.path = None;
+        self.result.cost = 0;
+        self.result.goal = None;
+        if self.g[start_index] == UNREACHED {
+            return;
+        }
+        let mut path = Vec::new();
+        let mut cost = 0;
+        let mut pos = self.start;
+        while !self.ends.contains(&pos) {
+            let Some((next, step)) = self
+                .successors(pos)
+                .into_iter()
+                .filter(|&(next, _)| self.g[self.grid.index(next)] != UNREACHED)
+                .min_by_key(|&(next, step)| self.g[self.grid.index(next)] + step)
+            else {
+                return;
+            };
+            // the scores are consistent along the way, but guard against a cycle regardless
+            if path.len() >= self.grid.len() {
+                return;
+            }
+            path.push(next);
+            cost += step;
+            pos = next;
+        }
+        self.result.path = Some(path);
+        self.result.cost = cost;
+        self.result.goal = Some(pos);
+    }
+}
+
+/// Expands straight segments between consecutive jump points into individual cells.
+fn fill_lines(start: Pos, jump_points: &[Pos]) -> Vec<Pos> {
+    let mut path = Vec::new();
+    let mut prev = start;
+    for &jump_point in jump_points {
+        let step = Pos(
+            (jump_point.0 - prev.0).signum(),
+            (jump_point.1 - prev.1).signum(),
+        );
+        while prev != jump_point {
+            prev = prev + step;
+            path.push(prev);
+        }
+    }
+    path
+}
+
+/// Cells on the Bresenham line from `from` to `to`, both included.
+pub fn bresenham(from: Pos, to: Pos) -> Vec<Pos> {
+    let (dr, dc) = ((to.0 - from.0).abs(), -(to.1 - from.1).abs());
+    let (sr, sc) = ((to.0 - from.0).signum(), (to.1 - from.1).signum());
+    let mut error = dr + dc;
+    let mut pos = from;
+    let mut line = vec![pos];
+    while pos != to {
+        let e2 = 2 * error;
+        if e2 >= dc {
+            error += dc;
+            pos.0 += sr;
+        }
+        if e2 <= dr {
+            error += dr;
+            pos.1 += sc;
+        }
+        line.push(pos);
+    }
+    line
+}
+
+/// Whether every cell on the line from `from` to `to` is passable.
+pub fn line_of_sight(grid: &Grid, from: Pos, to: Pos) -> bool {
+    bresenham(from, to)
+        .into_iter()
+        .all(|pos| grid.is_passable(pos))
+}
+
+/// Corners of `path` after dropping every point that the previous corner can see past,
+/// in the same form as the path: starting after `start` and ending at its last cell.
+/// Cells in `pinned` always stay, as do both ends of steps that jump across the grid.
+pub fn smooth_path(grid: &Grid, start: Pos, path: &[Pos], pinned: &[Pos]) -> Vec<Pos> {
+    let mut corners = Vec::new();
+    let mut anchor = start;
+    let mut prev = start;
+    for &pos in path {
+        if prev.chebyshev_distance(&pos) > 1 {
+            if prev != anchor {
+                corners.push(prev);
+            }
+            corners.push(pos);
+            anchor = pos;
+        } else if !line_of_sight(grid, anchor, pos) || (prev != anchor && pinned.contains(&prev)) {
+            corners.push(prev);
+            anchor = prev;
+        }
+        prev = pos;
+    }
+    if prev != anchor {
+        corners.push(prev);
+    }
+    corners
+}
+
+/// Marks every cell that can be walked to from `start` under `options`, indexed like the
+/// grid. Nothing is reachable from a wall.
+pub fn reachable(grid: &Grid, start: Pos, options: &Options) -> Vec<bool> {
+    let grid = &*options.search_grid(grid);
+    let mut reached = vec![false; grid.len()];
+    if !grid.is_passable(start) {
+        return reached;
+    }
+
+    reached[grid.index(start)] = true;
+    let mut queue = VecDeque::from([start]);
+    while let Some(curr) = queue.pop_front() {
+        let steps = options
+            .directions()
+            .iter()
+            .filter_map(|&direction| options.neighbor(grid, curr, direction));
+        let portal = grid
+            .portal_partner(curr)
+            .filter(|&partner| grid.is_passable(partner));
+        for next in steps.chain(portal) {
+            if !reached[grid.index(next)] {
+                reached[grid.index(next)] = true;
+                queue.push_back(next);
+            }
+        }
+    }
+    reached
+}
+
+/// Labels every passable cell with the connected region it belongs to, indexed like the grid,
+/// and returns the number of regions. Regions are numbered in row-major order of their first
+/// cell, and one-way cells are treated as ordinary ground.
+pub fn components(grid: &Grid, options: &Options) -> (Vec<Option<usize>>, usize) {
+    let mut labels = vec![None; grid.len()];
+    let mut count = 0;
+    for r in 0..grid.rows() as i64 {
+        for c in 0..grid.cols() as i64 {
+            let seed = Pos(r, c);
+            if !grid.is_passable(seed) || labels[grid.index(seed)].is_some() {
+                continue;
+            }
+
+            labels[grid.index(seed)] = Some(count);
+            let mut queue = VecDeque::from([seed]);
+            while let Some(curr) = queue.pop_front() {
+                let steps = options
+                    .directions()
+                    .iter()
+                    .filter_map(|&direction| options.adjacent(grid, curr, direction));
+                let portal = grid
+                    .portal_partner(curr)
+                    .filter(|&partner| grid.is_passable(partner));
+                for next in steps.chain(portal) {
+                    if labels[grid.index(next)].is_none() {
+                        labels[grid.index(next)] = Some(count);
+                        queue.push_back(next);
+                    }
+                }
+            }
+            count += 1;
+        }
+    }
+    (labels, count)
+}
+
+/// Manhattan distance from every cell to the nearest wall, indexed like the grid, or `None`
+/// for every cell if there are no walls.
+pub fn wall_distances(grid: &Grid) -> Vec<Option<u64>> {
+    let mut distances = vec![None; grid.len()];
+    let mut queue = VecDeque::new();
+    for r in 0..grid.rows() as i64 {
+        for c in 0..grid.cols() as i64 {
+            if grid.is_wall(Pos(r, c)) {
+                distances[grid.index(Pos(r, c))] = Some(0);
+                queue.push_back(Pos(r, c));
+            }
+        }
+    }
+
+    while let Some(curr) = queue.pop_front() {
+        let distance = distances[grid.index(curr)].unwrap() + 1;
+        for (dr, dc) in ORTHOGONAL_DIRECTIONS {
+            let next = curr + Pos(dr, dc);
+            if grid.contains(next) && distances[grid.index(next)].is_none() {
+                distances[grid.index(next)] = Some(distance);
+                queue.push_back(next);
+            }
+        }
+    }
+    distances
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A grid drawn with `#` for walls, `.` for plain ground and digits for terrain costs.
+    fn grid(rows: &[&str]) -> Grid {
+        let mut grid = Grid::new(rows.len() as u64, rows[0].len() as u64);
+        for (r, row) in rows.iter().enumerate() {
+            for (c, ch) in row.chars().enumerate() {
+                let cost = match ch {
+                    '#' => None,
+                    '.' => Some(1),
+                    _ => ch.to_digit(10).map(u64::from),
+                };
+                grid.set_cost(Pos(r as i64, c as i64), cost);
+            }
+        }
+        grid
+    }
+
+    #[test]
+    fn astar_follows_a_corridor() {
+        let grid = grid(&["#####", ".....", "#####"]);
+        let (path, cost) = astar(&grid, Pos(1, 0), Pos(1, 4)).unwrap();
+        assert_eq!(path, [Pos(1, 1), Pos(1, 2), Pos(1, 3), Pos(1, 4)]);
+        assert_eq!(cost, 4);
+    }
+
+    #[test]
+    fn astar_rejects_a_walled_goal() {
+        let grid = grid(&["...", "..#", "..."]);
+        assert_eq!(astar(&grid, Pos(0, 0), Pos(1, 2)), None);
+    }
+
+    #[test]
+    fn astar_finds_no_path_across_a_wall() {
+        let grid = grid(&["..#..", "..#..", "..#.."]);
+        assert_eq!(astar(&grid, Pos(0, 0), Pos(2, 4)), None);
+    }
+
+    fn solve(grid: &Grid, start: Pos, end: Pos, options: &Options) -> SearchResult {
+        let mut search = Search::new(grid, start, &[end], options);
+        search.run(grid);
+        search.result().clone()
+    }
+
+    #[test]
+    fn diagonals_only_cut_corners_when_allowed() {
+        let grid = grid(&["..", ".#"]);
+        let options = Options {
+            topology: Topology::Square8,
+            orthogonal_cost: SQRT2_ORTHOGONAL_COST,
+            diagonal_cost: SQRT2_DIAGONAL_COST,
+            ..Options::default()
+        };
+        let cutting = Options {
+            corner_cutting: true,
+            ..options
+        };
+        assert_eq!(
+            solve(&grid, Pos(1, 0), Pos(0, 1), &cutting).path.unwrap(),
+            [Pos(0, 1)]
+        );
+        assert_eq!(
+            solve(&grid, Pos(1, 0), Pos(0, 1), &options).path.unwrap(),
+            [Pos(0, 0), Pos(0, 1)]
+        );
+    }
+
+    #[test]
+    fn astar_stays_optimal_on_an_open_diagonal_grid() {
+        let grid = Grid::new(8, 8);
+        for (orthogonal_cost, diagonal_cost) in [(1, 2), (1, 1), (10, 14)] {
+            let options = Options {
+                topology: Topology::Square8,
+                orthogonal_cost,
+                diagonal_cost,
+                ..Options::default()
+            };
+            let dijkstra = Options {
+                algorithm: Algorithm::Dijkstra,
+                ..options
+            };
+            for end in [Pos(7, 7), Pos(7, 3), Pos(2, 6)] {
+                assert_eq!(
+                    solve(&grid, Pos(0, 0), end, &options).cost,
+                    solve(&grid, Pos(0, 0), end, &dijkstra).cost
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn auto_heuristic_never_overestimates() {
+        let grid = grid(&["......", ".##.#.", "...#..", ".#....", "...#.."]);
+        let end = Pos(4, 5);
+        for topology in [Topology::Square4, Topology::Square8, Topology::Hex] {
+            for (orthogonal_cost, diagonal_cost) in [(1, 2), (10, 14)] {
+                let options = Options {
+                    topology,
+                    orthogonal_cost,
+                    diagonal_cost,
+                    ..Options::default()
+                };
+                let dijkstra = Options {
+                    algorithm: Algorithm::Dijkstra,
+                    ..options
+                };
+                for r in 0..grid.rows() as i64 {
+                    for c in 0..grid.cols() as i64 {
+                        let result = solve(&grid, Pos(r, c), end, &dijkstra);
+                        if result.path.is_some() {
+                            assert!(options.heuristic(&grid, Pos(r, c), &[end]) <= result.cost);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn paths_go_around_costly_terrain() {
+        let grid = grid(&[".....", ".999."]);
+        let (path, cost) = astar(&grid, Pos(1, 0), Pos(1, 4)).unwrap();
+        assert_eq!(cost, 6);
+        assert!(path.iter().all(|&pos| grid.cost(pos) == Some(1)));
+    }
+
+    #[test]
+    fn a_walled_start_is_settled_before_queueing() {
+        let grid = grid(&["#..", "...", "..."]);
+        assert_eq!(astar(&grid, Pos(0, 0), Pos(2, 2)), None);
+
+        let search = Search::new(&grid, Pos(0, 0), &[Pos(2, 2)], &Options::default());
+        assert!(search.is_finished());
+        assert_eq!(search.open_len(), 0);
+        assert_eq!(search.result().numcalc, 0);
+    }
+
+    #[test]
+    fn high_gscore_tie_break_expands_no_more_cells() {
+        let grid = Grid::new(15, 15);
+        for topology in [Topology::Square4, Topology::Square8] {
+            let position = Options {
+                topology,
+                ..Options::default()
+            };
+            let high_gscore = Options {
+                tie_break: TieBreak::HighGscore,
+                ..position
+            };
+            let by_position = solve(&grid, Pos(0, 0), Pos(14, 14), &position);
+            let by_gscore = solve(&grid, Pos(0, 0), Pos(14, 14), &high_gscore);
+            assert_eq!(by_gscore.cost, by_position.cost);
+            assert!(by_gscore.numcalc <= by_position.numcalc);
+        }
+    }
+
+    #[test]
+    fn wrapping_edges_shortens_paths_across_them() {
+        let grid = Grid::new(5, 10);
+        let options = Options::default();
+        let wrapped = Options {
+            wrap: true,
+            ..options
+        };
+        assert_eq!(solve(&grid, Pos(2, 0), Pos(2, 9), &options).cost, 9);
+        let result = solve(&grid, Pos(2, 0), Pos(2, 9), &wrapped);
+        assert_eq!(result.path.unwrap(), [Pos(2, 9)]);
+        assert_eq!(result.cost, 1);
+        assert_eq!(solve(&grid, Pos(0, 0), Pos(4, 9), &wrapped).cost, 2);
+    }
+
+    #[test]
+    fn scaled_diagonal_costs_track_euclidean_length() {
+        let grid = Grid::new(10, 10);
+        let options = Options {
+            topology: Topology::Square8,
+            orthogonal_cost: SQRT2_ORTHOGONAL_COST,
+            diagonal_cost: SQRT2_DIAGONAL_COST,
+            ..Options::default()
+        };
+        for end in [Pos(1, 1), Pos(5, 5), Pos(9, 9)] {
+            let cost = solve(&grid, Pos(0, 0), end, &options).cost as f64;
+            let length = Pos(0, 0).euclidean_distance(&end) * SQRT2_ORTHOGONAL_COST as f64;
+            assert!(
+                cost <= length && cost >= length * 0.98,
+                "{cost} vs {length}"
+            );
+        }
+    }
+
+    #[test]
+    fn start_on_the_end_is_a_path_of_no_steps() {
+        let grid = Grid::new(3, 3);
+        assert_eq!(astar(&grid, Pos(1, 1), Pos(1, 1)), Some((Vec::new(), 0)));
+
+        let search = Search::new(&grid, Pos(1, 1), &[Pos(1, 1)], &Options::default());
+        assert!(search.is_finished());
+        assert_eq!(search.result().goal, Some(Pos(1, 1)));
+        assert_eq!(search.result().numcalc, 0);
+    }
+
+    #[test]
+    fn cells_are_expanded_at_most_once() {
+        let mut rng = crate::rng::Rng::new(7);
+        let mut weighted = Grid::new(12, 12);
+        for r in 0..12 {
+            for c in 0..12 {
+                weighted.set_cost(Pos(r, c), Some(1 + rng.below(9) as u64));
+            }
+        }
+        for grid in [Grid::new(12, 12), weighted] {
+            for algorithm in [Algorithm::AStar, Algorithm::Dijkstra] {
+                for topology in [Topology::Square4, Topology::Square8] {
+                    let options = Options {
+                        algorithm,
+                        topology,
+                        ..Options::default()
+                    };
+                    let mut search = Search::new(&grid, Pos(0, 0), &[Pos(11, 11)], &options);
+                    let mut popped = Vec::new();
+                    while !search.step(&grid) {
+                        popped.extend(search.recent_expansions().last());
+                    }
+                    let closed = (0..12)
+                        .flat_map(|r| (0..12).map(move |c| Pos(r, c)))
+                        .filter(|&pos| search.is_closed(&grid, pos))
+                        .count();
+                    let expanded = popped.len();
+                    popped.sort_by_key(|pos| (pos.0, pos.1));
+                    popped.dedup();
+                    assert_eq!(popped.len(), expanded);
+                    // the goal is expanded by the final step
+                    assert_eq!(closed, expanded + 1);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn weighted_paths_match_dijkstra() {
+        let grid = grid(&["19111", "19191", "11191", "99991", "11111"]);
+        for topology in [Topology::Square4, Topology::Square8] {
+            let options = Options {
+                topology,
+                ..Options::default()
+            };
+            let dijkstra = Options {
+                algorithm: Algorithm::Dijkstra,
+                ..options
+            };
+            let astar = solve(&grid, Pos(0, 0), Pos(4, 0), &options);
+            assert_eq!(
+                astar.cost,
+                solve(&grid, Pos(0, 0), Pos(4, 0), &dijkstra).cost
+            );
+        }
+    }
+
+    #[test]
+    fn replanning_starts_over_when_the_heuristic_changes() {
+        let grid = Grid::new(5, 5);
+        let options = Options::default();
+        let dstar = DStarLite::new(&grid, Pos(0, 0), &[Pos(4, 4)], &options);
+        assert!(dstar.can_replan(&grid, &[Pos(4, 4)], &options));
+        let euclidean = Options {
+            heuristic: Heuristic::Euclidean,
+            ..options
+        };
+        assert!(!dstar.can_replan(&grid, &[Pos(4, 4)], &euclidean));
+    }
+
+    #[test]
+    fn jump_points_stay_optimal_with_dear_diagonals() {
+        let grid = Grid::new(3, 3);
+        for diagonal_cost in [1, 2, 3] {
+            let options = Options {
+                algorithm: Algorithm::Jps,
+                topology: Topology::Square8,
+                diagonal_cost,
+                ..Options::default()
+            };
+            let dijkstra = Options {
+                algorithm: Algorithm::Dijkstra,
+                ..options
+            };
+            for end in [Pos(1, 1), Pos(2, 2), Pos(2, 1)] {
+                assert_eq!(
+                    solve(&grid, Pos(0, 0), end, &options).cost,
+                    solve(&grid, Pos(0, 0), end, &dijkstra).cost
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn nearest_passable_counts_steps_of_the_topology() {
+        // on hexes (1, 0) is one step from (0, 1), while (0, 3) is two
+        let hexes = grid(&["###.", ".###", "####"]);
+        let hex = Options {
+            topology: Topology::Hex,
+            ..Options::default()
+        };
+        assert_eq!(hex.nearest_passable(&hexes, Pos(0, 1)), Some(Pos(1, 0)));
+        assert_eq!(hex.nearest_passable(&hexes, Pos(1, 0)), Some(Pos(1, 0)));
+
+        let edges = grid(&["....#####", "#########"]);
+        let wrapped = Options {
+            wrap: true,
+            ..Options::default()
+        };
+        assert_eq!(wrapped.nearest_passable(&edges, Pos(1, 7)), Some(Pos(0, 0)));
+        assert_eq!(
+            Options::default().nearest_passable(&edges, Pos(1, 7)),
+            Some(Pos(0, 3))
+        );
+        assert_eq!(
+            Options::default().nearest_passable(&Grid::new(1, 1), Pos(3, 3)),
+            None
+        );
+    }
+
+    #[test]
+    fn theta_sight_does_not_squeeze_between_corners() {
+        let grid = grid(&[".#", "#."]);
+        let options = Options {
+            algorithm: Algorithm::ThetaStar,
+            topology: Topology::Square8,
+            ..Options::default()
+        };
+        assert!(line_of_sight(&grid, Pos(0, 0), Pos(1, 1)));
+        assert!(!options.theta_line_of_sight(&grid, Pos(0, 0), Pos(1, 1)));
+        let cutting = Options {
+            corner_cutting: true,
+            ..options
+        };
+        assert!(cutting.theta_line_of_sight(&grid, Pos(0, 0), Pos(1, 1)));
+    }
+}
@@ -0,0 +1,35 @@
+//! A small seeded random number generator, so that generated maps can be reproduced.
+
+/// Xorshift64 generator. Its state doubles as a seed: a generator created from
+/// `Rng::new(rng.seed())` produces the same numbers as `rng` from that point on.
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        // xorshift never leaves the all-zero state
+        let state = if seed == 0 {
+            0x9e37_79b9_7f4a_7c15
+        } else {
+            seed
+        };
+        Self { state }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.state
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state
+    }
+
+    /// Uniformly chosen number in `0..n`, up to a bias too small to matter for map generation.
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+}
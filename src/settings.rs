@@ -0,0 +1,263 @@
+//! Preferences kept between runs.
+//!
+//! ```text
+//! zoom 0.1
+//! size 20 20
+//! algorithm AStar
+//! topology Square8
+//! ```
+//!
+//! Each line is a key followed by its value. Missing, unknown or malformed lines are skipped,
+//! leaving that setting at its default.
+
+use std::fmt::Write as _;
+use std::path::PathBuf;
+
+use crate::pathfind::{
+    Algorithm, DEFAULT_COLS, DEFAULT_ROWS, Heuristic, OpenList, Options, TieBreak, Topology,
+};
+
+pub struct Settings {
+    pub zoom: f32,
+    /// On-screen width and height of a cell relative to the zoom.
+    pub cell_w: f32,
+    pub cell_h: f32,
+    pub rows: u64,
+    pub cols: u64,
+    pub options: Options,
+    pub animate: bool,
+    /// Whether searches without animation run a budget of expansions per frame.
+    pub responsive: bool,
+    pub speed: u32,
+    /// Whether holding `S` or `E` moves the endpoint along with the cursor, rather than a key
+    /// press placing it at the next click.
+    pub hold_to_place: bool,
+    /// Whether an endpoint placed on a wall moves to the nearest open cell.
+    pub snap_to_open: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            zoom: 0.1,
+            cell_w: 1.0,
+            cell_h: 1.0,
+            rows: DEFAULT_ROWS,
+            cols: DEFAULT_COLS,
+            options: Options::default(),
+            animate: false,
+            responsive: false,
+            speed: 1,
+            hold_to_place: false,
+            snap_to_open: false,
+        }
+    }
+}
+
+/// `$XDG_CONFIG_HOME/pathfind/settings`, falling back to `~/.config`.
+pub fn path() -> Option<PathBuf> {
+    let config = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config.join("pathfind").join("settings"))
+}
+
+/// A number that is neither infinite nor NaN, which would pass through any clamping.
+fn parse_finite<T: std::str::FromStr + Into<f64> + Copy>(word: &str) -> Option<T> {
+    word.parse()
+        .ok()
+        .filter(|&value: &T| value.into().is_finite())
+}
+
+fn parse_algorithm(word: &str) -> Option<Algorithm> {
+    Some(match word {
+        "AStar" => Algorithm::AStar,
+        "Dijkstra" => Algorithm::Dijkstra,
+        "Bfs" => Algorithm::Bfs,
+        "Bidirectional" => Algorithm::Bidirectional,
+        "Jps" => Algorithm::Jps,
+        "GreedyBestFirst" => Algorithm::GreedyBestFirst,
+        "ThetaStar" => Algorithm::ThetaStar,
+        _ => return None,
+    })
+}
+
+fn parse_topology(word: &str) -> Option<Topology> {
+    Some(match word {
+        "Square4" => Topology::Square4,
+        "Square8" => Topology::Square8,
+        "Hex" => Topology::Hex,
+        _ => return None,
+    })
+}
+
+fn parse_heuristic(word: &str) -> Option<Heuristic> {
+    Some(match word {
+        "Auto" => Heuristic::Auto,
+        "Manhattan" => Heuristic::Manhattan,
+        "Euclidean" => Heuristic::Euclidean,
+        "Chebyshev" => Heuristic::Chebyshev,
+        "Zero" => Heuristic::Zero,
+        _ => return None,
+    })
+}
+
+fn parse_tie_break(word: &str) -> Option<TieBreak> {
+    Some(match word {
+        "Position" => TieBreak::Position,
+        "HighGscore" => TieBreak::HighGscore,
+        _ => return None,
+    })
+}
+
+fn parse_open_list(word: &str) -> Option<OpenList> {
+    Some(match word {
+        "Heap" => OpenList::Heap,
+        "Scan" => OpenList::Scan,
+        _ => return None,
+    })
+}
+
+pub fn to_string(settings: &Settings) -> String {
+    let options = &settings.options;
+    let mut s = String::new();
+    writeln!(s, "zoom {}", settings.zoom).unwrap();
+    writeln!(s, "cell_size {} {}", settings.cell_w, settings.cell_h).unwrap();
+    writeln!(s, "size {} {}", settings.rows, settings.cols).unwrap();
+    writeln!(s, "algorithm {:?}", options.algorithm).unwrap();
+    writeln!(s, "topology {:?}", options.topology).unwrap();
+    writeln!(
+        s,
+        "step_costs {} {}",
+        options.orthogonal_cost, options.diagonal_cost
+    )
+    .unwrap();
+    writeln!(s, "corner_cutting {}", options.corner_cutting).unwrap();
+    writeln!(s, "heuristic {:?}", options.heuristic).unwrap();
+    writeln!(s, "heuristic_weight {}", options.heuristic_weight).unwrap();
+    writeln!(s, "clearance_weight {}", options.clearance_weight).unwrap();
+    writeln!(s, "clearance_radius {}", options.clearance_radius).unwrap();
+    writeln!(s, "tie_break {:?}", options.tie_break).unwrap();
+    writeln!(s, "open_list {:?}", options.open_list).unwrap();
+    writeln!(s, "wrap {}", options.wrap).unwrap();
+    writeln!(s, "animate {}", settings.animate).unwrap();
+    writeln!(s, "responsive {}", settings.responsive).unwrap();
+    writeln!(s, "speed {}", settings.speed).unwrap();
+    writeln!(s, "hold_to_place {}", settings.hold_to_place).unwrap();
+    writeln!(s, "snap_to_open {}", settings.snap_to_open).unwrap();
+    s
+}
+
+pub fn parse(s: &str) -> Settings {
+    let mut settings = Settings::default();
+    let options = &mut settings.options;
+    for line in s.lines() {
+        let mut words = line.split_whitespace();
+        let (Some(key), Some(value)) = (words.next(), words.next()) else {
+            continue;
+        };
+        match key {
+            "zoom" => settings.zoom = parse_finite(value).unwrap_or(settings.zoom),
+            "cell_size" => {
+                if let (Some(w), Some(Some(h))) =
+                    (parse_finite(value), words.next().map(parse_finite))
+                {
+                    (settings.cell_w, settings.cell_h) = (w, h);
+                }
+            }
+            "size" => {
+                if let (Ok(rows), Some(Ok(cols))) = (value.parse(), words.next().map(str::parse)) {
+                    (settings.rows, settings.cols) = (rows, cols);
+                }
+            }
+            "algorithm" => options.algorithm = parse_algorithm(value).unwrap_or(options.algorithm),
+            "topology" => options.topology = parse_topology(value).unwrap_or(options.topology),
+            "step_costs" => {
+                if let (Ok(orthogonal), Some(Ok(diagonal))) =
+                    (value.parse(), words.next().map(str::parse))
+                {
+                    (options.orthogonal_cost, options.diagonal_cost) = (orthogonal, diagonal);
+                }
+            }
+            "corner_cutting" => {
+                options.corner_cutting = value.parse().unwrap_or(options.corner_cutting)
+            }
+            "heuristic" => options.heuristic = parse_heuristic(value).unwrap_or(options.heuristic),
+            "heuristic_weight" => {
+                options.heuristic_weight = parse_finite(value).unwrap_or(options.heuristic_weight)
+            }
+            "clearance_weight" => {
+                options.clearance_weight = value.parse().unwrap_or(options.clearance_weight)
+            }
+            "clearance_radius" => {
+                options.clearance_radius = value.parse().unwrap_or(options.clearance_radius)
+            }
+            "tie_break" => options.tie_break = parse_tie_break(value).unwrap_or(options.tie_break),
+            "open_list" => options.open_list = parse_open_list(value).unwrap_or(options.open_list),
+            "wrap" => options.wrap = value.parse().unwrap_or(options.wrap),
+            "animate" => settings.animate = value.parse().unwrap_or(settings.animate),
+            "responsive" => settings.responsive = value.parse().unwrap_or(settings.responsive),
+            "speed" => settings.speed = value.parse().unwrap_or(settings.speed),
+            "hold_to_place" => {
+                settings.hold_to_place = value.parse().unwrap_or(settings.hold_to_place)
+            }
+            "snap_to_open" => {
+                settings.snap_to_open = value.parse().unwrap_or(settings.snap_to_open)
+            }
+            _ => {}
+        }
+    }
+    settings
+}
+
+/// Reads the settings file, or returns the defaults if there is none.
+pub fn load() -> Settings {
+    path()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .map_or_else(Settings::default, |s| parse(&s))
+}
+
+pub fn save(settings: &Settings) -> std::io::Result<()> {
+    let path = path().ok_or_else(|| std::io::Error::other("no config directory"))?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, to_string(settings))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn settings_read_back_as_written() {
+        let mut settings = Settings {
+            zoom: 0.25,
+            rows: 40,
+            cols: 30,
+            animate: true,
+            speed: 8,
+            snap_to_open: true,
+            ..Settings::default()
+        };
+        settings.options.algorithm = Algorithm::ThetaStar;
+        settings.options.topology = Topology::Square8;
+        settings.options.heuristic = Heuristic::Chebyshev;
+        settings.options.heuristic_weight = 1.5;
+        settings.options.open_list = OpenList::Scan;
+
+        let parsed = parse(&to_string(&settings));
+        assert_eq!(to_string(&parsed), to_string(&settings));
+        assert_eq!(parsed.options.algorithm, Algorithm::ThetaStar);
+        assert_eq!((parsed.rows, parsed.cols), (40, 30));
+    }
+
+    #[test]
+    fn bad_lines_keep_the_defaults() {
+        let settings = parse(
+            "zoom NaN\ncell_size inf 2\nsize 10\nalgorithm Magic\nheuristic_weight -inf\n\
+             speed fast\nunknown 3\nwrap\n",
+        );
+        assert_eq!(to_string(&settings), to_string(&Settings::default()));
+    }
+}